@@ -1,7 +1,7 @@
 use milim_web::{context::Context, macros::handler, request::HttpRequest, run_app, server};
 
 #[handler]
-async fn hello(req: &HttpRequest, ctx: &Context) -> &'static str {
+async fn hello(_req: &HttpRequest, _ctx: &Context) -> &'static str {
     "Hello World!!"
 }
 
@@ -3,7 +3,7 @@ use quote::quote;
 use syn::{ItemFn, parse_macro_input};
 
 #[proc_macro_attribute]
-pub fn handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(item as ItemFn);
 
     let vis = &input_fn.vis;
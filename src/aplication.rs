@@ -1,165 +1,249 @@
-use std::{collections::HashMap, io::Result, sync::Arc};
-
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpListener,
-};
+use std::{borrow::Cow, collections::HashMap, io::Result, sync::Arc};
 
 use crate::{
     config::Config,
     context::Context,
+    error::ParseError,
     fairing::{Fairing, IntoFairing},
     guard::Outcome,
-    request::{HttpRequest, HttpRequestData, Method, Resource},
+    query::QueryMap,
+    request::{ConnectionInfo, HttpRequest, HttpRequestData, Method, Resource},
     response::HttpResponse,
-    router::{RouteBuilder, Router},
+    router::{RouteBuilder, Router, Scope},
+    server::Server,
+    shutdown::ShutdownHandle,
     status::StatusCode,
+    tls::TlsConfig,
+    tree::RouteTree,
 };
 
 pub struct App {
-    routes: Vec<Router>,
-    context: Arc<Context>,
-    config: Config,
-    fairings: Vec<Arc<dyn Fairing>>,
+    routes: RouteTree,
+    pub(crate) context: Arc<Context>,
+    pub(crate) config: Config,
+    pub(crate) fairings: Vec<Arc<dyn Fairing>>,
+    pub(crate) shutdown: ShutdownHandle,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
     pub fn new() -> Self {
         Self {
-            routes: Vec::new(),
+            routes: RouteTree::default(),
             context: Arc::new(Context {
                 map: HashMap::new(),
+                routes: HashMap::new(),
             }),
             config: Config::new(),
             fairings: Vec::new(),
+            shutdown: ShutdownHandle::new(),
         }
     }
     /// # Usado para adicionar um middleware global ele sera executado antes dos de rota
     pub fn fairing<M: IntoFairing>(&mut self, middleware: M) {
         self.fairings.push(middleware.into_fairing());
     }
-    /// Adiciona uma rota
+    /// Adiciona uma rota.
+    ///
+    /// Segmentos do pattern: `:nome` captura um segmento, `:nome?` e opcional,
+    /// `*nome` captura o restante do caminho e `**` tambem aceita nenhum segmento.
+    /// Opcionais e catch-all so podem aparecer no fim do pattern.
+    ///
+    /// Parametros aceitam restrições como `:id<u64>`, `:uuid<uuid>` ou uma regex,
+    /// `:slug<[a-z0-9-]+>`, quando o valor não e valido a busca segue para outras rotas.
+    /// A restrição e aplicada a um unico segmento ja decodificado, então uma `/` na
+    /// regex so casa com `%2F`, para varios segmentos use `*nome`
     pub fn route<'a>(&'a mut self, method: Method, path: &str) -> RouteBuilder<'a> {
         RouteBuilder {
             app: self,
             pattern: path.to_owned(),
             method,
             guards: Vec::new(),
+            name: None,
+        }
+    }
+    /// Registra um grupo de rotas com o prefixo `prefix`, veja `Scope`
+    pub fn scope<F>(&mut self, prefix: &str, f: F)
+    where
+        F: FnOnce(&mut Scope),
+    {
+        let mut scope = Scope::new(prefix);
+        f(&mut scope);
+        for route in scope.into_routes() {
+            self.add_route(route);
+        }
+    }
+    /// Monta as rotas de outro App sob `prefix`.
+    ///
+    /// Os fairings globais de `app` passam a valer apenas para as rotas dele e o
+    /// estado registrado com `manage` e adicionado a este App, quando os dois
+    /// registram o mesmo tipo o valor deste App e mantido. Config e
+    /// `ShutdownHandle` de `app` são descartados
+    /// ``` rust
+    /// use std::sync::Arc;
+    ///
+    /// use milim_web::{context::Context, request::Method::*, run_app, server, testing::TestClient};
+    ///
+    /// struct Currency(&'static str);
+    ///
+    /// let mut billing = server();
+    /// billing.manage(Currency("BRL"));
+    /// billing.route(Get, "/invoices").handler(|_req, ctx: Arc<Context>| async move {
+    ///     format!("faturas em {}", ctx.get_state::<Currency>().unwrap().0)
+    /// });
+    ///
+    /// let mut app = server();
+    /// app.mount("/billing", billing);
+    ///
+    /// run_app(|| async {
+    ///     let client = TestClient::new(app).await;
+    ///     let res = client.get("/billing/invoices").send().await;
+    ///     assert_eq!(res.get_body_text(), "faturas em BRL");
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Se o contexto de um dos Apps estiver compartilhado, o que so acontece
+    /// depois do App ser movido para o servidor
+    pub fn mount(&mut self, prefix: &str, app: App) {
+        let App {
+            routes,
+            context,
+            fairings,
+            ..
+        } = app;
+        for mut route in routes.into_routes() {
+            route.pattern.prefix(prefix);
+            route.fairings.splice(0..0, fairings.iter().cloned());
+            self.add_route(route);
+        }
+        let state = Arc::into_inner(context)
+            .expect("Nao e possivel montar um App com o contexto compartilhado")
+            .map;
+        let context = Arc::get_mut(&mut self.context)
+            .expect("Nao e possivel montar um App com o servidor em execucao");
+        for (key, value) in state {
+            context.map.entry(key).or_insert(value);
         }
     }
+    /// Substitui a configuração do servidor
+    pub fn config(&mut self, config: Config) {
+        self.config = config;
+    }
+    /// Atende as conexões com HTTPS usando o certificado e a chave de `tls`
+    pub fn tls(&mut self, tls: TlsConfig) {
+        self.config.tls = Some(tls);
+    }
     pub fn manage<T: Send + Sync + 'static>(&mut self, state: T) {
-        Arc::get_mut(&mut self.context).unwrap().state(state);
+        Arc::get_mut(&mut self.context)
+            .expect("Nao e possivel registrar estado com o servidor em execucao")
+            .state(state);
     }
     pub(crate) fn add_route(&mut self, route: Router) {
-        self.routes.push(route);
+        if let Some(name) = &route.name {
+            let context = Arc::get_mut(&mut self.context)
+                .expect("Nao e possivel registrar rotas com o servidor em execucao");
+            if context
+                .routes
+                .insert(name.clone(), route.pattern.clone())
+                .is_some()
+            {
+                panic!("Ja existe uma rota com o nome {:?}", name);
+            }
+        }
+        self.routes.insert(route);
+    }
+    /// Fairings globais seguidos dos fairings de scopes, cada um uma unica vez,
+    /// usados para `on_ready` e `on_shutdown`
+    pub(crate) fn lifecycle_fairings(&self) -> Vec<Arc<dyn Fairing>> {
+        let mut fairings = self.fairings.clone();
+        for fairing in self.routes.iter().flat_map(|route| route.fairings.iter()) {
+            if !fairings.iter().any(|f| Arc::ptr_eq(f, fairing)) {
+                fairings.push(Arc::clone(fairing));
+            }
+        }
+        fairings
+    }
+    /// Handle que encerra o servidor iniciado por `listen` ou `listen_with_shutdown`
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
     }
-    /// Inicia um servidor http sync
+    /// Inicia um servidor http async, cada conexão e atendida em sua propria task.
+    ///
+    /// O App e movido para o servidor, apos o encerramento ele fica vazio
     pub async fn listen(&mut self, adress: &str) -> Result<()> {
-        println!(" > Max body size: {}KB", self.config.max_body_kb);
-        println!(" > Keep alive: {}s", self.config.keep_alive_s);
-        println!(" > Max headers: {}", self.config.max_headers);
-        let listener = TcpListener::bind(adress).await?;
+        self.listen_with_shutdown(adress, std::future::pending())
+            .await
+    }
+    /// Inicia o servidor e o encerra quando `signal` completar ou o `ShutdownHandle` for acionado
+    pub async fn listen_with_shutdown<F>(&mut self, adress: &str, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let server = std::mem::take(self).bind(adress).await?;
+        server.serve_with_shutdown(signal).await
+    }
+    /// Cria um `Server` escutando em `adress`, use a porta 0 para uma porta livre
+    pub async fn bind(self, adress: &str) -> Result<Server> {
+        Server::bind(self, adress).await
+    }
 
-        let App {
-            routes,
-            context,
-            config,
-            fairings,
-        } = self;
-        for fairings in fairings.iter() {
-            fairings.on_ready(Arc::get_mut(context).unwrap()).await;
+    /// Executa roteamento, fairings, guards e handler para uma requisição
+    pub(crate) async fn dispatch(
+        &self,
+        req_data: HttpRequestData,
+        conn: ConnectionInfo,
+    ) -> HttpResponse {
+        let mut req = HttpRequest::with_connection(req_data, conn);
+
+        let Resource::Path(resource) = &req.raw.resource;
+        let (path, query) = split_path_query(resource);
+        let (route, params) = match self.routes.find(&req.raw.method, path) {
+            Ok(found) => found,
+            Err(StatusCode::NotFound) => {
+                return HttpResponse::new(StatusCode::NotFound, None, "Not Found");
+            }
+            Err(status) => return HttpResponse::new(status, None, ""),
+        };
+        let queryes = match QueryMap::parse(query.unwrap_or("")) {
+            Ok(queryes) => queryes,
+            Err(e) => return HttpResponse::new(e.status_code(), None, ""),
+        };
+        req.raw.params = Some(params);
+        req.raw.queryes = queryes;
+
+        // Executando os Fairings e registra em executed para executar on_response
+        let mut executed = Vec::new();
+        for fairing in self.fairings.iter().chain(route.fairings.iter()) {
+            fairing.on_request(&mut req, &self.context).await;
+            executed.push(Arc::clone(fairing));
         }
-        loop {
-            let (mut socket, _) = listener.accept().await?;
-
-            let mut buf = vec![0u8; Config::get_kb_value(config.max_body_kb)];
-
-            match socket.read(&mut buf).await {
-                Ok(n) if n > 0 => {
-                    let raw = String::from_utf8_lossy(&buf[..n]).to_string();
-
-                    let req_data = HttpRequestData::from(raw);
-                    let mut req = HttpRequest::new(req_data);
-
-                    let path = match &req.raw.resource {
-                        Resource::Path(p) => p.clone(),
-                    };
-
-                    let mut handled = false;
-                    let mut method_mismatch = false;
-                    for route in routes.iter() {
-                        if let Some((params, queryes)) = match_route(&route.pattern, &path) {
-                            if route.method != req.raw.method {
-                                method_mismatch = true;
-                                continue;
-                            }
-                            req.raw.params = Some(params);
-                            req.raw.queryes = Some(queryes);
-                            let mut req_arc = Arc::new(req);
-
-                            let mut res = HttpResponse::new(StatusCode::Ok, None, "");
-                            let mut executed = Vec::new();
-
-                            // Executando os Fairings e registra em executed para executar on_response
-                            for fairings in fairings.iter() {
-                                fairings
-                                    .on_request(Arc::get_mut(&mut req_arc).unwrap(), context)
-                                    .await;
-                                executed.push(Arc::clone(fairings));
-                            }
-                            let mut outcome = Outcome::Success;
-                            // Executa os guards
-                            for guard in route.guards.iter() {
-                                outcome = guard
-                                    .from_request(&req_arc, Arc::get_mut(context).unwrap())
-                                    .await;
-                                if outcome != Outcome::Success {
-                                    break;
-                                }
-                            }
-                            if let Outcome::Failure(response) = outcome {
-                                res = response;
-                            } else {
-                                let response =
-                                    (route.handler)(Arc::clone(&req_arc), Arc::clone(context))
-                                        .await;
-                                res = response.into_response();
-                            }
-                            for f in executed.iter() {
-                                f.on_response(&req_arc, &mut res, context).await;
-                            }
-                            let res_string: String = res.into();
-
-                            socket.write_all(res_string.as_bytes()).await?;
-
-                            handled = true;
-                            break;
-                        }
-                    }
-                    if handled {
-                        continue;
-                    }
-                    if method_mismatch {
-                        let res: String =
-                            HttpResponse::new(StatusCode::MethodNotAllowed, None, "".to_string())
-                                .into();
-                        let _ = socket.write_all(res.as_bytes()).await?;
-                        continue;
-                    } else {
-                        let res: String =
-                            HttpResponse::new(StatusCode::NotFound, None, "Not Found").into();
-                        let _ = socket.write_all(res.as_bytes()).await?;
-                        continue;
-                    }
-                }
-                Ok(_) => {
-                    println!("Not found")
-                }
-                Err(_) => {
-                    println!("Erro")
-                }
-            };
+        let req = Arc::new(req);
+
+        let mut outcome = Outcome::Success;
+        // Executa os guards
+        for guard in route.guards.iter() {
+            outcome = guard.from_request(&req, &self.context).await;
+            if outcome != Outcome::Success {
+                break;
+            }
+        }
+        let mut res = if let Outcome::Failure(response) = outcome {
+            response
+        } else {
+            let response = (route.handler)(Arc::clone(&req), Arc::clone(&self.context)).await;
+            response.into_response()
+        };
+        for f in executed.iter() {
+            f.on_response(&req, &mut res, &self.context).await;
         }
+        res
     }
 }
 
@@ -168,55 +252,174 @@ fn split_path_query(s: &str) -> (&str, Option<&str>) {
     if let Some(pos) = s.find("?") {
         (&s[..pos], Some(&s[pos + 1..]))
     } else {
-        (&s, None)
+        (s, None)
     }
 }
 
-/// Faz o parse da query obtendo a chave e valor
-fn parse_query(q: Option<&str>) -> HashMap<String, String> {
-    let mut map = HashMap::new();
-    if let Some(qs) = q {
-        for pair in qs.split("&") {
-            if pair.is_empty() {
-                continue;
-            }
-            let mut it = pair.splitn(2, "=");
-            let key = it.next().unwrap_or("");
-            let val = it.next().unwrap_or("");
-            map.insert(key.to_string(), val.to_string());
+/// Codifica tudo que não for um caractere não reservado da RFC 3986
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
         }
     }
-    map
+    out
 }
 
-/// Ainda não implementado
-fn _percent_decode(_s: &str) -> String {
-    String::new()
+/// Decodifica os escapes `%XX` da RFC 3986, com `plus_as_space` o `+` vira espaço
+/// como em query strings
+pub(crate) fn percent_decode(
+    s: &str,
+    plus_as_space: bool,
+) -> std::result::Result<Cow<'_, str>, ParseError> {
+    if !(s.contains('%') || plus_as_space && s.contains('+')) {
+        return Ok(Cow::Borrowed(s));
+    }
+    let invalid = || ParseError::InvalidEncoding(s.to_string());
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                // `from_str_radix` aceitaria um `+` no lugar do primeiro digito
+                let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return Err(invalid());
+                }
+                let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+                out.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out)
+        .map(Cow::Owned)
+        .map_err(|_| invalid())
 }
 
-/// Obtem as rotas e os parametros da requisição
-fn match_route(
-    pattern: &str,
-    path: &str,
-) -> Option<(HashMap<String, String>, HashMap<String, String>)> {
-    let (path_only, query_opt) = split_path_query(path);
-    let pat_parts: Vec<&str> = pattern.trim_matches('/').split('/').collect();
-    let path_parts: Vec<&str> = path_only.trim_matches('/').split('/').collect();
-    if pat_parts.len() != path_parts.len() {
-        return None;
-    }
-    let mut params = HashMap::new();
-    for (pp, rp) in pat_parts.iter().zip(path_parts.iter()) {
-        if pp.starts_with(":") {
-            let name = pp.trim_start_matches(":").to_string();
-            println!(">> Param: {}\n>> Value: {}", name, rp);
-            params.insert(name, rp.to_string());
-        } else if pp != rp {
-            return None;
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::{percent_decode, percent_encode};
+    use crate::{
+        context::Context,
+        error::ParseError,
+        fairing::Fairing,
+        request::{HttpRequest, Method::*},
+        response::HttpResponse,
+        server,
+        status::StatusCode,
+        testing::TestClient,
+    };
+
+    #[test]
+    fn decodes_escapes_and_plus() {
+        assert_eq!(percent_decode("a%20b%2Fc", false).unwrap(), "a b/c");
+        assert_eq!(percent_decode("%C3%A9", false).unwrap(), "é");
+        assert_eq!(percent_decode("a+b", false).unwrap(), "a+b");
+        assert_eq!(percent_decode("a+b%2B", true).unwrap(), "a b+");
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        for input in ["%", "%4", "%zz", "%+4", "%-1", "%C3", "%FF"] {
+            assert_eq!(
+                percent_decode(input, false),
+                Err(ParseError::InvalidEncoding(input.to_string())),
+                "{}",
+                input
+            );
         }
     }
 
-    let query_params = parse_query(query_opt);
+    #[test]
+    fn encodes_everything_but_unreserved() {
+        assert_eq!(percent_encode("aZ0-._~"), "aZ0-._~");
+        assert_eq!(percent_encode("a b/é?"), "a%20b%2F%C3%A9%3F");
+        assert_eq!(
+            percent_decode(&percent_encode("a b/é?"), false).unwrap(),
+            "a b/é?"
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_path_segments_after_routing() {
+        let mut app = server();
+        app.route(Get, "/u/:id")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let tag = req.raw.get_query("tag").unwrap_or_default();
+                format!("{}|{}", req.raw.get_param("id").unwrap_or_default(), tag)
+            });
+        app.route(Get, "/café")
+            .handler(|_req, _ctx| async move { "static" });
+        let client = TestClient::new(app).await;
+
+        let res = client.get("/u/a%2Fb%20c?tag=x+y%26z").send().await;
+        assert_eq!(res.get_body_text(), "a/b c|x y&z");
+        // Um `%2F` não cria um novo segmento
+        let res = client.get("/u/a%2Fb/c").send().await;
+        assert_eq!(res.status(), &StatusCode::NotFound);
+        let res = client.get("/caf%C3%A9").send().await;
+        assert_eq!(res.get_body_text(), "static");
+        let res = client.get("/u/%+41").send().await;
+        assert_eq!(res.status(), &StatusCode::BadRequest);
+        let res = client.get("/u/a?tag=%zz").send().await;
+        assert_eq!(res.status(), &StatusCode::BadRequest);
+    }
+
+    struct Header(&'static str);
 
-    Some((params, query_params))
+    #[async_trait]
+    impl Fairing for Header {
+        async fn on_request(&self, _req: &mut HttpRequest, _ctx: &Context) {}
+        async fn on_response(&self, _req: &HttpRequest, res: &mut HttpResponse, _ctx: &Context) {
+            res.append_header("X-Fairing", self.0).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn mounted_fairings_stay_scoped_and_parent_state_wins() {
+        let mut child = server();
+        child.fairing(Header("child"));
+        child.manage(1u32);
+        child.manage("child");
+        child
+            .route(Get, "/items")
+            .handler(|_req, ctx: Arc<Context>| async move {
+                let number = ctx.get_state::<u32>().unwrap();
+                format!("{} {}", number, ctx.get_state::<&str>().unwrap())
+            });
+
+        let mut app = server();
+        app.fairing(Header("app"));
+        app.manage(2u32);
+        app.route(Get, "/").handler(|_req, _ctx| async move { "" });
+        app.mount("/child", child);
+        let client = TestClient::new(app).await;
+
+        let res = client.get("/child/items").send().await;
+        // O u32 registrado pelos dois Apps fica com o valor do App pai
+        assert_eq!(res.get_body_text(), "2 child");
+        let fairings: Vec<_> = res.headers().get_all("X-Fairing").collect();
+        assert_eq!(fairings, ["app", "child"]);
+
+        let res = client.get("/").send().await;
+        let fairings: Vec<_> = res.headers().get_all("X-Fairing").collect();
+        assert_eq!(fairings, ["app"]);
+    }
 }
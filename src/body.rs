@@ -0,0 +1,175 @@
+use std::{fmt, io, pin::Pin};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Stream de pedaços do body, um erro interrompe o envio da resposta
+pub type BodyStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + 'static>>;
+
+/// Body de uma resposta, em memoria ou gerado aos poucos.
+///
+/// Bodies em stream são escritos conforme os pedaços ficam prontos, com
+/// `Transfer-Encoding: chunked` quando o `Content-Length` não foi definido
+/// ``` rust
+/// use milim_web::{body::Body, bytes::Bytes};
+///
+/// let rows = (0..3).map(|i| Ok(Bytes::from(format!("{},linha\n", i))));
+/// let body = Body::from_stream(futures::stream::iter(rows));
+/// ```
+pub enum Body {
+    Full(Bytes),
+    Stream(BodyStream),
+}
+
+impl Body {
+    /// Body gerado por um stream de `Bytes`
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        Body::Stream(Box::pin(stream))
+    }
+    /// Body lido de um `AsyncRead`, como um arquivo, em pedaços de ate 8KB
+    pub fn from_reader<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        Body::from_stream(stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            let mut buf = vec![0u8; 8192];
+            match reader.read(&mut buf).await {
+                Ok(0) => None,
+                Ok(n) => {
+                    buf.truncate(n);
+                    Some((Ok(Bytes::from(buf)), Some(reader)))
+                }
+                // Depois de um erro o stream termina
+                Err(e) => Some((Err(e), None)),
+            }
+        }))
+    }
+    /// Indica se o tamanho do body e conhecido antes do envio
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Body::Stream(_))
+    }
+    /// Le o stream inteiro para a memoria
+    pub async fn collect(self) -> io::Result<Bytes> {
+        match self {
+            Body::Full(bytes) => Ok(bytes),
+            Body::Stream(mut stream) => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    buf.extend_from_slice(&chunk?);
+                }
+                Ok(Bytes::from(buf))
+            }
+        }
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Body::Full(Bytes::new())
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(value: Bytes) -> Self {
+        Body::Full(value)
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Full(bytes) => f.debug_tuple("Full").field(bytes).finish(),
+            Body::Stream(_) => f.debug_tuple("Stream").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Streams nunca são iguais, apenas bodies em memoria são comparados
+impl PartialEq for Body {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Body::Full(a), Body::Full(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Cursor},
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::StreamExt;
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf},
+        net::TcpStream,
+    };
+
+    use super::Body;
+    use crate::{request::Method::*, server};
+
+    #[tokio::test]
+    async fn reader_body_is_sent_in_chunks() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let expected = data.clone();
+        let mut app = server();
+        app.route(Get, "/export").handler(move |_req, _ctx| {
+            let data = data.clone();
+            async move { Body::from_reader(Cursor::new(data)) }
+        });
+        let server = app.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /export HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+
+        let head_end = out.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&out[..head_end]);
+        assert!(head.contains("Transfer-Encoding:chunked\r\n"));
+        assert!(head.contains("Content-Type:application/octet-stream\r\n"));
+        assert!(!head.contains("Content-Length"));
+
+        // Pedaços de ate 8KB lidos do reader
+        let mut body = b"2000\r\n".to_vec();
+        body.extend_from_slice(&expected[..8192]);
+        body.extend_from_slice(b"\r\n710\r\n");
+        body.extend_from_slice(&expected[8192..]);
+        body.extend_from_slice(b"\r\n0\r\n\r\n");
+        assert!(out[head_end..] == body[..]);
+    }
+
+    #[tokio::test]
+    async fn reader_error_ends_the_stream() {
+        struct Failing;
+
+        impl AsyncRead for Failing {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                _buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            }
+        }
+
+        let Body::Stream(mut stream) = Body::from_reader(Failing) else {
+            panic!("from_reader deve criar um stream");
+        };
+        assert!(matches!(stream.next().await, Some(Err(_))));
+        assert!(stream.next().await.is_none());
+    }
+}
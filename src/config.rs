@@ -1,3 +1,5 @@
+use crate::tls::TlsConfig;
+
 /// Usado para configurar o servidor
 #[derive(Debug, Clone, Default)]
 pub struct Config {
@@ -6,6 +8,10 @@ pub struct Config {
     pub max_body_kb: usize,
     pub read_timeout_s: u32,
     pub keep_alive_s: u32,
+    /// Tempo que as requisições em andamento tem para terminar no encerramento
+    pub shutdown_grace_s: u32,
+    /// Quando definido as conexões são atendidas com HTTPS
+    pub tls: Option<TlsConfig>,
 }
 
 impl Config {
@@ -16,6 +22,8 @@ impl Config {
             max_body_kb: 1024,
             read_timeout_s: 5,
             keep_alive_s: 5,
+            shutdown_grace_s: 30,
+            tls: None,
         }
     }
     pub fn get_kb_value(value_kb: usize) -> usize {
@@ -2,11 +2,19 @@ use std::{
     any::{Any, TypeId},
     collections::HashMap,
 };
+
+use crate::{error::UrlError, tree::Pattern};
 #[derive(Debug)]
 pub struct RequestContext {
     data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
 }
 
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RequestContext {
     pub fn new() -> Self {
         Self {
@@ -32,6 +40,8 @@ impl RequestContext {
 #[derive(Debug)]
 pub struct Context {
     pub(crate) map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    /// Patterns das rotas com nome, usados por `url_for`
+    pub(crate) routes: HashMap<String, Pattern>,
 }
 
 impl Context {
@@ -43,4 +53,142 @@ impl Context {
             .get(&TypeId::of::<T>())
             .and_then(|boxed| boxed.downcast_ref())
     }
+    /// Gera o caminho da rota registrada com `name`, os valores são percent-encoded
+    /// ``` rust
+    /// use std::sync::Arc;
+    ///
+    /// use milim_web::{
+    ///     context::Context,
+    ///     request::{HttpRequest, Method::*},
+    ///     run_app, server,
+    ///     testing::TestClient,
+    /// };
+    ///
+    /// let mut app = server();
+    /// app.route(Get, "/users/:id").name("user_show").handler(|_req, _ctx| async move { "" });
+    /// app.route(Post, "/users").handler(|_req: Arc<HttpRequest>, ctx: Arc<Context>| async move {
+    ///     ctx.url_for("user_show", &[("id", "ana maria")]).unwrap()
+    /// });
+    ///
+    /// run_app(|| async {
+    ///     let client = TestClient::new(app).await;
+    ///     let res = client.post("/users").send().await;
+    ///     assert_eq!(res.get_body_text(), "/users/ana%20maria");
+    /// });
+    /// ```
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        self.routes
+            .get(name)
+            .ok_or_else(|| UrlError::UnknownRoute(name.to_string()))?
+            .url(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        error::UrlError,
+        request::{HttpRequest, Method::*},
+        server,
+        testing::TestClient,
+    };
+
+    fn url_for(pattern: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        let mut app = server();
+        app.route(Get, pattern)
+            .name("route")
+            .handler(|_req, _ctx| async move { "" });
+        app.context.url_for("route", params)
+    }
+
+    #[test]
+    fn builds_encoded_urls() {
+        assert_eq!(url_for("/", &[]), Ok("/".into()));
+        assert_eq!(
+            url_for("/users/:id/posts/:slug", &[("slug", "a b/c"), ("id", "7")]),
+            Ok("/users/7/posts/a%20b%2Fc".into())
+        );
+        assert_eq!(
+            url_for("/files/*path", &[("path", "/docs/é.txt")]),
+            Ok("/files/docs/%C3%A9.txt".into())
+        );
+        assert_eq!(url_for("/posts/:id?", &[]), Ok("/posts".into()));
+        assert_eq!(url_for("/static/**", &[]), Ok("/static".into()));
+    }
+
+    #[test]
+    fn reports_unknown_route() {
+        let app = server();
+        assert_eq!(
+            app.context.url_for("nope", &[]),
+            Err(UrlError::UnknownRoute("nope".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_extra_params() {
+        assert_eq!(
+            url_for("/users/:id", &[]),
+            Err(UrlError::MissingParam("id".into()))
+        );
+        assert_eq!(
+            url_for("/users/:id", &[("id", "1"), ("page", "2")]),
+            Err(UrlError::ExtraParam("page".into()))
+        );
+        // Um opcional so pode ser omitido junto com os seguintes
+        assert_eq!(
+            url_for("/d/:year?/:month?", &[("month", "5")]),
+            Err(UrlError::MissingParam("year".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_params() {
+        let invalid = |name: &str, value: &str| UrlError::InvalidParam {
+            name: name.into(),
+            value: value.into(),
+        };
+        assert_eq!(
+            url_for("/items/:id<u64>", &[("id", "abc")]),
+            Err(invalid("id", "abc"))
+        );
+        assert_eq!(url_for("/items/:id", &[("id", "")]), Err(invalid("id", "")));
+        assert_eq!(
+            url_for("/files/*path", &[("path", "/")]),
+            Err(invalid("path", ""))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Ja existe uma rota com o nome")]
+    fn rejects_duplicate_route_names() {
+        let mut app = server();
+        app.route(Get, "/a")
+            .name("same")
+            .handler(|_req, _ctx| async move { "" });
+        app.route(Get, "/b")
+            .name("same")
+            .handler(|_req, _ctx| async move { "" });
+    }
+
+    #[tokio::test]
+    async fn generated_url_resolves_to_same_params() {
+        let mut app = server();
+        app.route(Get, "/tags/:tag")
+            .name("tag")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                req.raw.get_param("tag").unwrap_or_default()
+            });
+        let url = app
+            .context
+            .url_for("tag", &[("tag", "c++ & rust/é")])
+            .unwrap();
+        let client = TestClient::new(app).await;
+        assert_eq!(
+            client.get(&url).send().await.get_body_text(),
+            "c++ & rust/é"
+        );
+    }
 }
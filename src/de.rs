@@ -0,0 +1,287 @@
+use std::vec;
+
+use serde::{
+    Deserialize,
+    de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor, value::SeqDeserializer},
+    forward_to_deserialize_any,
+};
+
+use crate::error::DeserializeError;
+
+/// Deserializa `T` a partir das chaves e seus valores em texto, usado por
+/// `HttpRequest::query` e `HttpRequest::params`
+pub(crate) fn from_pairs<'de, T>(
+    pairs: Vec<(&'de str, Vec<&'de str>)>,
+) -> Result<T, DeserializeError>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(PairsDeserializer {
+        pairs: pairs.into_iter(),
+        current: None,
+    })
+}
+
+struct PairsDeserializer<'de> {
+    pairs: vec::IntoIter<(&'de str, Vec<&'de str>)>,
+    current: Option<(&'de str, Vec<&'de str>)>,
+}
+
+impl<'de> de::Deserializer<'de> for PairsDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for PairsDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, values)) = self.pairs.next() else {
+            return Ok(None);
+        };
+        self.current = Some((key, values));
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (key, values) = self
+            .current
+            .take()
+            .ok_or_else(|| de::Error::custom("value without key"))?;
+        // Erros do valor passam a indicar o campo
+        seed.deserialize(ValueDeserializer { values })
+            .map_err(|e| e.with_field(key))
+    }
+}
+
+/// Valores de uma chave, campos simples usam o primeiro e sequencias usam todos
+struct ValueDeserializer<'de> {
+    values: Vec<&'de str>,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    fn first(&self) -> &'de str {
+        self.values.first().copied().unwrap_or("")
+    }
+    fn parse<T: std::str::FromStr>(&self) -> Result<T, DeserializeError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        let value = self.first();
+        value
+            .parse()
+            .map_err(|e| de::Error::custom(format!("invalid value {:?}: {}", value, e)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.first())
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    /// Um valor vazio, como em `?page=`, e tratado como ausente
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.first().is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let values = self.values.into_iter().map(|value| ValueDeserializer {
+            values: vec![value],
+        });
+        visitor.visit_seq(SeqDeserializer::new(values))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.first().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde::Deserialize;
+
+    use super::from_pairs;
+    use crate::{
+        error::DeserializeError,
+        request::{HttpRequest, Method::*},
+        responder::Responder,
+        server,
+        status::StatusCode,
+        testing::TestClient,
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Page {
+        page: u32,
+        #[serde(default)]
+        tag: Vec<String>,
+        sort: Option<String>,
+    }
+
+    #[test]
+    fn deserializes_typed_fields_and_sequences() {
+        let page: Page = from_pairs(vec![("page", vec!["2"]), ("tag", vec!["a", "b"])]).unwrap();
+        assert_eq!(
+            page,
+            Page {
+                page: 2,
+                tag: vec!["a".into(), "b".into()],
+                sort: None,
+            }
+        );
+    }
+
+    #[test]
+    fn empty_value_is_none() {
+        let page: Page = from_pairs(vec![("page", vec!["1"]), ("sort", vec![""])]).unwrap();
+        assert_eq!(page.sort, None);
+        let page: Page = from_pairs(vec![("page", vec!["1"]), ("sort", vec!["name"])]).unwrap();
+        assert_eq!(page.sort.as_deref(), Some("name"));
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let err = from_pairs::<Page>(vec![("page", vec!["abc"])]).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("page"));
+        let err = from_pairs::<Page>(vec![("tag", vec!["a"])]).unwrap_err();
+        assert_eq!(
+            err,
+            DeserializeError {
+                field: Some("page".into()),
+                message: "missing field".into(),
+            }
+        );
+    }
+
+    fn app() -> crate::aplication::App {
+        let mut app = server();
+        app.route(Get, "/posts")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let res: Box<dyn Responder + Send> = match req.query::<Page>() {
+                    Ok(page) => Box::new(format!("{} {:?} {:?}", page.page, page.tag, page.sort)),
+                    Err(e) => Box::new(e),
+                };
+                res
+            });
+        app.route(Get, "/users/:id/:slug")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                #[derive(Deserialize)]
+                struct User {
+                    id: u64,
+                    slug: String,
+                }
+                let res: Box<dyn Responder + Send> = match req.params::<User>() {
+                    Ok(user) => Box::new(format!("{} {}", user.id, user.slug)),
+                    Err(e) => Box::new(e),
+                };
+                res
+            });
+        app
+    }
+
+    #[tokio::test]
+    async fn query_and_params_reach_handlers_typed() {
+        let client = TestClient::new(app()).await;
+
+        let res = client.get("/posts?page=3&tag=a&tag=b&sort=").send().await;
+        assert_eq!(res.get_body_text(), r#"3 ["a", "b"] None"#);
+        let res = client.get("/users/7/ana-maria").send().await;
+        assert_eq!(res.get_body_text(), "7 ana-maria");
+    }
+
+    #[tokio::test]
+    async fn invalid_or_missing_field_answers_bad_request() {
+        let client = TestClient::new(app()).await;
+
+        for path in ["/posts?page=abc", "/posts?tag=a", "/users/x/ana"] {
+            let res = client.get(path).send().await;
+            assert_eq!(res.status(), &StatusCode::BadRequest, "{}", path);
+            assert_eq!(
+                res.header("Content-Type"),
+                Some("text/plain; charset=utf-8")
+            );
+        }
+        let res = client.get("/posts?page=abc").send().await;
+        assert!(res.get_body_text().starts_with("invalid field page: "));
+        let res = client.get("/posts?tag=a").send().await;
+        assert_eq!(res.get_body_text(), "invalid field page: missing field");
+        let res = client.get("/users/x/ana").send().await;
+        assert!(res.get_body_text().starts_with("invalid field id: "));
+    }
+}
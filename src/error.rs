@@ -1,2 +1,228 @@
-#[derive(Debug, PartialEq)]
-pub enum ServerError {}
+use std::{fmt, io};
+
+use crate::status::StatusCode;
+
+/// Erros que podem acontecer ao ler uma requisição da conexão
+#[derive(Debug)]
+pub enum ServerError {
+    /// Erro de entrada e saida no socket
+    Io(io::Error),
+    /// O cliente não enviou a requisição completa dentro de `read_timeout_s`
+    Timeout,
+    /// O body excede `max_body_kb`
+    PayloadTooLarge,
+    /// O head excede `max_header_len_kb` ou `max_headers`
+    HeaderTooLarge,
+    /// O header Content-Length não e um numero valido
+    InvalidContentLength,
+    /// O body chunked esta mal formado ou usa uma codificação não suportada
+    InvalidTransferEncoding,
+    /// O head da requisição e invalido
+    Parse(ParseError),
+}
+
+impl ServerError {
+    /// Status que deve ser enviado ao cliente antes de fechar a conexão
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            ServerError::Io(_) => None,
+            ServerError::Timeout => Some(StatusCode::RequestTimeout),
+            ServerError::PayloadTooLarge => Some(StatusCode::PayloadTooLarge),
+            ServerError::HeaderTooLarge => Some(StatusCode::RequestHeaderFieldsTooLarge),
+            ServerError::InvalidContentLength | ServerError::InvalidTransferEncoding => {
+                Some(StatusCode::BadRequest)
+            }
+            ServerError::Parse(e) => Some(e.status_code()),
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::Io(e) => write!(f, "io error: {}", e),
+            ServerError::Timeout => write!(f, "request timeout"),
+            ServerError::PayloadTooLarge => write!(f, "payload too large"),
+            ServerError::HeaderTooLarge => write!(f, "request header fields too large"),
+            ServerError::InvalidContentLength => write!(f, "invalid content-length"),
+            ServerError::InvalidTransferEncoding => write!(f, "invalid transfer-encoding"),
+            ServerError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<io::Error> for ServerError {
+    fn from(value: io::Error) -> Self {
+        ServerError::Io(value)
+    }
+}
+
+impl From<ParseError> for ServerError {
+    fn from(value: ParseError) -> Self {
+        ServerError::Parse(value)
+    }
+}
+
+/// Erros ao fazer o parse do head de uma requisição
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    /// O head não e UTF-8 valido
+    InvalidUtf8,
+    /// A linha da requisição não tem o formato `METODO caminho VERSAO`
+    InvalidRequestLine,
+    /// Metodo desconhecido
+    InvalidMethod(String),
+    /// A linha da requisição não informa a versão do HTTP
+    MissingVersion,
+    /// Versão do HTTP não suportada pelo servidor
+    UnsupportedVersion(String),
+    /// Linha de header sem `:` ou com nome invalido
+    InvalidHeader(String),
+    /// Caminho ou query com escape `%` invalido ou que não resulta em UTF-8
+    InvalidEncoding(String),
+}
+
+impl ParseError {
+    /// Status enviado ao cliente, 505 para versão não suportada e 400 para o resto
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ParseError::UnsupportedVersion(_) => StatusCode::HttpVersionNotSupported,
+            _ => StatusCode::BadRequest,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidUtf8 => write!(f, "request head is not valid utf-8"),
+            ParseError::InvalidRequestLine => write!(f, "invalid request line"),
+            ParseError::InvalidMethod(m) => write!(f, "invalid method: {}", m),
+            ParseError::MissingVersion => write!(f, "missing http version"),
+            ParseError::UnsupportedVersion(v) => write!(f, "unsupported http version: {}", v),
+            ParseError::InvalidHeader(h) => write!(f, "invalid header line: {}", h),
+            ParseError::InvalidEncoding(s) => write!(f, "invalid percent-encoding: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Erros ao obter um parametro da rota com `HttpRequest::param`
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParamError {
+    /// A rota não capturou o parametro
+    Missing(String),
+    /// O valor não pode ser convertido para o tipo pedido
+    Invalid { name: String, value: String },
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamError::Missing(name) => write!(f, "missing route param: {}", name),
+            ParamError::Invalid { name, value } => {
+                write!(f, "invalid value for route param {}: {}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// Erros ao gerar a URL de uma rota com `Context::url_for`
+#[derive(Debug, PartialEq, Clone)]
+pub enum UrlError {
+    /// Nenhuma rota registrada com o nome
+    UnknownRoute(String),
+    /// Parametro obrigatorio da rota não informado
+    MissingParam(String),
+    /// Parametro que não existe no pattern da rota
+    ExtraParam(String),
+    /// Valor que não satisfaz a restrição do parametro
+    InvalidParam { name: String, value: String },
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::UnknownRoute(name) => write!(f, "unknown route name: {}", name),
+            UrlError::MissingParam(name) => write!(f, "missing route param: {}", name),
+            UrlError::ExtraParam(name) => write!(f, "unknown route param: {}", name),
+            UrlError::InvalidParam { name, value } => {
+                write!(f, "invalid value for route param {}: {}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+/// Erro ao deserializar a query ou os parametros da rota, respondido com 400
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeserializeError {
+    /// Campo com valor invalido ou ausente, quando conhecido
+    pub field: Option<String>,
+    pub message: String,
+}
+
+#[cfg(feature = "serde")]
+impl DeserializeError {
+    pub(crate) fn with_field(mut self, field: &str) -> Self {
+        self.field.get_or_insert_with(|| field.to_string());
+        self
+    }
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::BadRequest
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "invalid field {}: {}", field, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for DeserializeError {}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            field: None,
+            message: msg.to_string(),
+        }
+    }
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            field: Some(field.to_string()),
+            message: "missing field".to_string(),
+        }
+    }
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+        Self {
+            field: Some(field.to_string()),
+            message: "unknown field".to_string(),
+        }
+    }
+}
+
+/// Codigo de status fora do intervalo 100 a 999
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct InvalidStatusCode(pub u16);
+
+impl fmt::Display for InvalidStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid status code: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidStatusCode {}
@@ -6,9 +6,11 @@ use crate::{context::Context, request::HttpRequest, response::HttpResponse};
 
 #[async_trait]
 pub trait Fairing: Send + Sync + 'static {
-    async fn on_ready(&self, ctx: &mut Context) {}
+    async fn on_ready(&self, _ctx: &mut Context) {}
     async fn on_request(&self, req: &mut HttpRequest, ctx: &Context);
     async fn on_response(&self, req: &HttpRequest, res: &mut HttpResponse, ctx: &Context);
+    /// Executado quando o servidor termina de encerrar, depois das requisições em andamento
+    async fn on_shutdown(&self, _ctx: &Context) {}
 }
 
 pub trait IntoFairing {
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 
 use crate::{context::Context, request::HttpRequest, response::HttpResponse};
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq)]
 pub enum Outcome {
     Success,
     Failure(HttpResponse),
@@ -12,7 +12,8 @@ pub enum Outcome {
 
 #[async_trait]
 pub trait Guard: Send + Sync + 'static {
-    async fn from_request(&self, req: &HttpRequest, ctx: &mut Context) -> Outcome;
+    #[allow(clippy::wrong_self_convention)]
+    async fn from_request(&self, req: &HttpRequest, ctx: &Context) -> Outcome;
 }
 
 pub trait IntoGuard {
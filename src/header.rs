@@ -0,0 +1,123 @@
+use std::fmt;
+
+/// Mapa de headers http.
+///
+/// Os nomes são comparados sem diferenciar maiusculas e minusculas, mas mantem
+/// a grafia original. Um mesmo nome pode ter varios valores, na ordem em que foram adicionados
+/// ``` rust
+/// use milim_web::header::HeaderMap;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.append("Set-Cookie", "a=1");
+/// headers.append("set-cookie", "b=2");
+/// assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+/// assert_eq!(headers.get_all("Set-Cookie").collect::<Vec<_>>(), ["a=1", "b=2"]);
+///
+/// headers.insert("Set-Cookie", "c=3");
+/// assert_eq!(headers.get_all("Set-Cookie").count(), 1);
+/// ```
+#[derive(Clone, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+    /// Primeiro valor do header
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+    /// Todos os valores do header na ordem em que foram adicionados
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+    /// Substitui todos os valores do header por `value`
+    pub fn insert(&mut self, name: &str, value: &str) {
+        self.remove(name);
+        self.append(name, value);
+    }
+    /// Adiciona mais um valor ao header mantendo os existentes
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.entries.push((name.to_string(), value.to_string()));
+    }
+    /// Remove todos os valores do header, retorna se algum foi removido
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        len != self.entries.len()
+    }
+    /// Percorre os pares nome e valor na ordem em que foram adicionados
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Debug for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut headers = HeaderMap::new();
+        for (k, v) in iter {
+            headers.append(k.as_ref(), v.as_ref());
+        }
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderMap;
+
+    #[test]
+    fn names_ignore_case_and_keep_spelling() {
+        let mut headers = HeaderMap::new();
+        headers.append("X-Request-Id", "1");
+        assert_eq!(headers.get("x-request-id"), Some("1"));
+        assert!(headers.contains_key("X-REQUEST-ID"));
+        assert_eq!(headers.iter().collect::<Vec<_>>(), [("X-Request-Id", "1")]);
+    }
+
+    #[test]
+    fn keeps_values_in_insertion_order() {
+        let mut headers: HeaderMap = [("Vary", "Accept"), ("Host", "a"), ("vary", "Origin")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            headers.get_all("VARY").collect::<Vec<_>>(),
+            ["Accept", "Origin"]
+        );
+        assert_eq!(headers.len(), 3);
+
+        headers.insert("vary", "Cookie");
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("Host", "a"), ("vary", "Cookie")]
+        );
+        assert!(headers.remove("HOST"));
+        assert!(!headers.remove("Host"));
+        assert_eq!(headers.len(), 1);
+    }
+}
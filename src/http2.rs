@@ -0,0 +1,323 @@
+use std::{future::poll_fn, io, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use futures::StreamExt;
+use h2::{
+    Reason, RecvStream,
+    server::{self, SendResponse},
+};
+use tokio::{
+    sync::watch,
+    task::JoinSet,
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    aplication::App,
+    body::Body,
+    config::Config,
+    error::{ParseError, ServerError},
+    header::HeaderMap,
+    query::QueryMap,
+    request::{ConnectionInfo, HttpRequestData, Method, Resource, Version},
+    response::HttpResponse,
+    status::StatusCode,
+    upgrade::Io,
+};
+
+/// Inicio do preface de HTTP/2, lido pelo parser de HTTP/1.1 como um head completo
+pub(crate) const PREFACE_HEAD: &[u8] = b"PRI * HTTP/2.0\r\n\r\n";
+
+/// Headers da conexão HTTP/1.1 que não podem ser enviados em HTTP/2
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Atende uma conexão HTTP/2, cada stream e despachado em sua propria task
+/// para o mesmo roteamento, fairings e guards do HTTP/1.1
+pub(crate) async fn serve_connection<S: Io>(
+    app: &Arc<App>,
+    io: S,
+    conn: ConnectionInfo,
+    mut draining: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let mut h2 = server::Builder::new()
+        .max_header_list_size(Config::get_kb_value(app.config.max_header_len_kb) as u32)
+        .handshake::<_, Bytes>(io)
+        .await
+        .map_err(into_io)?;
+
+    let mut streams = JoinSet::new();
+    let mut closing = false;
+    loop {
+        // Aceitar streams tambem envia os dados pendentes da conexão
+        let next = tokio::select! {
+            next = h2.accept() => next,
+            _ = draining.wait_for(|draining| *draining), if !closing => {
+                // GOAWAY, os streams em andamento terminam normalmente
+                h2.graceful_shutdown();
+                closing = true;
+                continue;
+            }
+        };
+        match next {
+            Some(Ok((request, respond))) => {
+                let app = Arc::clone(app);
+                let conn = conn.clone();
+                streams.spawn(async move {
+                    let _ = handle_stream(&app, request, respond, conn).await;
+                });
+                while streams.try_join_next().is_some() {}
+            }
+            Some(Err(e)) => return Err(into_io(e)),
+            None => break,
+        }
+    }
+    while streams.join_next().await.is_some() {}
+    Ok(())
+}
+
+async fn handle_stream(
+    app: &App,
+    request: http::Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    conn: ConnectionInfo,
+) -> Result<(), h2::Error> {
+    let head_only = request.method() == http::Method::HEAD;
+    let res = match read_request(app, request).await {
+        Ok(req_data) => app.dispatch(req_data, conn).await,
+        Err(e) => match e.status_code() {
+            Some(status) => HttpResponse::new(status, None, ""),
+            None => {
+                respond.send_reset(Reason::CANCEL);
+                return Ok(());
+            }
+        },
+    };
+    send_response(respond, res, head_only).await
+}
+
+/// Converte os headers e le o body do stream dentro de `read_timeout_s`
+async fn read_request(
+    app: &App,
+    request: http::Request<RecvStream>,
+) -> Result<HttpRequestData, ServerError> {
+    let (parts, mut recv) = request.into_parts();
+
+    let method = Method::from(parts.method.as_str());
+    if method == Method::Uninitialized {
+        return Err(ParseError::InvalidMethod(parts.method.to_string()).into());
+    }
+    let resource = parts
+        .uri
+        .path_and_query()
+        .map_or("/", |path| path.as_str())
+        .to_string();
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in parts.headers.iter() {
+        headers.append(name.as_str(), &String::from_utf8_lossy(value.as_bytes()));
+    }
+    // Em HTTP/2 o host vem no pseudo-header :authority
+    if let Some(authority) = parts.uri.authority()
+        && !headers.contains_key("Host")
+    {
+        headers.insert("Host", authority.as_str());
+    }
+
+    let max_body = Config::get_kb_value(app.config.max_body_kb);
+    let deadline = Instant::now() + Duration::from_secs(app.config.read_timeout_s.into());
+    let mut body = Vec::new();
+    loop {
+        let chunk = match timeout_at(deadline, recv.data()).await {
+            Ok(Some(chunk)) => chunk.map_err(into_io)?,
+            Ok(None) => break,
+            Err(_) => return Err(ServerError::Timeout),
+        };
+        let _ = recv.flow_control().release_capacity(chunk.len());
+        if body.len() + chunk.len() > max_body {
+            return Err(ServerError::PayloadTooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let mut trailers = HeaderMap::new();
+    match timeout_at(deadline, recv.trailers()).await {
+        Ok(received) => {
+            for (name, value) in received.map_err(into_io)?.iter().flatten() {
+                trailers.append(name.as_str(), &String::from_utf8_lossy(value.as_bytes()));
+            }
+        }
+        Err(_) => return Err(ServerError::Timeout),
+    }
+
+    Ok(HttpRequestData {
+        method,
+        version: Version::V2_0,
+        resource: Resource::Path(resource),
+        headers,
+        body: Bytes::from(body),
+        trailers,
+        params: None,
+        queryes: QueryMap::new(),
+    })
+}
+
+async fn send_response(
+    mut respond: SendResponse<Bytes>,
+    mut res: HttpResponse,
+    head_only: bool,
+) -> Result<(), h2::Error> {
+    // Não existe troca de protocolo em HTTP/2 e o 101 e proibido, RFC 9113 8.6.
+    // Rotas como WebSocket precisam de HTTP/1.1
+    if res.take_upgrade().is_some() || *res.status() == StatusCode::SwitchingProtocols {
+        res = HttpResponse::new(StatusCode::HttpVersionNotSupported, None, "");
+    }
+
+    let status = *res.status();
+    let no_body = head_only
+        || status == StatusCode::NoContent
+        || status == StatusCode::NotModified
+        || status.is_informational();
+    let body = res.take_body();
+
+    let mut builder = http::Response::builder().status(status.as_u16());
+    for (name, value) in res.headers().iter() {
+        if CONNECTION_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        builder = builder.header(name, value);
+    }
+    if let Body::Full(bytes) = &body
+        && !res.headers().contains_key("Content-Length")
+        && !no_body
+    {
+        builder = builder.header("content-length", bytes.len());
+    }
+    let response = match builder.body(()) {
+        Ok(response) => response,
+        Err(_) => {
+            // Header invalido para HTTP/2
+            let response = http::Response::builder()
+                .status(StatusCode::InternalServerError.as_u16())
+                .body(())
+                .expect("Resposta sem headers sempre e valida");
+            respond.send_response(response, true)?;
+            return Ok(());
+        }
+    };
+
+    let end_now = no_body || matches!(&body, Body::Full(bytes) if bytes.is_empty());
+    let mut send = respond.send_response(response, end_now)?;
+    if end_now {
+        return Ok(());
+    }
+    match body {
+        Body::Full(bytes) => send_all(&mut send, bytes).await?,
+        Body::Stream(mut stream) => {
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => send_all(&mut send, chunk).await?,
+                    Err(_) => {
+                        send.send_reset(Reason::INTERNAL_ERROR);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+    send.send_data(Bytes::new(), true)
+}
+
+/// Envia `data` respeitando o controle de fluxo do stream
+async fn send_all(send: &mut h2::SendStream<Bytes>, mut data: Bytes) -> Result<(), h2::Error> {
+    while !data.is_empty() {
+        send.reserve_capacity(data.len());
+        let capacity = match poll_fn(|cx| send.poll_capacity(cx)).await {
+            Some(capacity) => capacity?,
+            None => return Err(Reason::CANCEL.into()),
+        };
+        let chunk = data.split_to(capacity.min(data.len()));
+        send.send_data(chunk, false)?;
+    }
+    Ok(())
+}
+
+fn into_io(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().expect("Erro de io")
+    } else {
+        io::Error::other(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio::{io::duplex, sync::watch};
+
+    use super::serve_connection;
+    use crate::{
+        aplication::App,
+        request::{ConnectionInfo, HttpRequest, Method::*},
+        response::HttpResponse,
+        server,
+        status::StatusCode,
+    };
+
+    /// Envia um GET por HTTP/2 e retorna o status e o body
+    async fn get(app: &Arc<App>, path: &str) -> (u16, Bytes) {
+        let app = Arc::clone(app);
+        let (client, server) = duplex(64 * 1024);
+        let (_draining, draining) = watch::channel(false);
+        tokio::spawn(async move {
+            let _ = serve_connection(&app, server, ConnectionInfo::default(), draining).await;
+        });
+
+        let (mut h2, connection) = h2::client::handshake(client).await.unwrap();
+        tokio::spawn(connection);
+        let request = http::Request::get(format!("http://localhost{}", path))
+            .body(())
+            .unwrap();
+        let (response, _) = h2.send_request(request, true).unwrap();
+        let (parts, mut body) = response.await.unwrap().into_parts();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        (parts.status.as_u16(), Bytes::from(data))
+    }
+
+    #[tokio::test]
+    async fn serves_routes_over_h2() {
+        let mut app = server();
+        app.route(Get, "/hello/:name")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                format!("Ola {}", req.raw.get_param("name").unwrap_or_default())
+            });
+        let app = Arc::new(app);
+        assert_eq!(get(&app, "/hello/ana").await, (200, Bytes::from("Ola ana")));
+    }
+
+    #[tokio::test]
+    async fn never_sends_switching_protocols() {
+        let mut app = server();
+        app.route(Get, "/upgrade").handler(|_req, _ctx| async move {
+            HttpResponse::new(StatusCode::SwitchingProtocols, None, "")
+        });
+        app.route(Get, "/ws")
+            .websocket(|_req, _ctx, _ws| async move {});
+
+        let app = Arc::new(app);
+        let (status, _) = get(&app, "/upgrade").await;
+        assert_eq!(status, 505);
+        // Sem os headers de upgrade, proibidos em HTTP/2, o handshake não acontece
+        let (status, _) = get(&app, "/ws").await;
+        assert_ne!(status, 101);
+    }
+}
@@ -19,69 +19,68 @@ milim-web = { version = "0.1.0", git = "https://github.com/DevMilim/milim-web" }
 
 ```
 
-``` rust
-use milim_web::request::Method::*;
-use milim_web::server;
+``` rust,no_run
+use milim_web::{context::Context, macros::handler, request::HttpRequest, run_app, server};
+
+#[handler]
+async fn hello(req: &HttpRequest, _ctx: &Context) -> String {
+    // Obtem o parametro name definido na rota
+    let name = req.raw.get_param("name").unwrap_or("".to_string());
+    format!("O valor de name e: {}", name)
+}
 
 fn main() {
+    use milim_web::request::Method::*;
     // Cria uma instancia de App
     let mut app = server();
 
-    app.route(Get, "/", |req, res, ctx| {
-        // Obtem a query name se definida
-        let query = req.get_query("name").unwrap_or("".to_string());
-        res.body(&format!("Valor da query name e: {}", query));
-    });
     // Cria uma rota dinamica com o parametro name
-    app.route(Get, "/:name", |req, res, ctx| {
-        res.body(&format!(
-            "O valor de name e: {}",
-            req.get_param("name").unwrap_or("".to_string())
-        ));
-    });
+    app.route(Get, "/:name").handler(hello);
 
     // Inicia o servidor Http na porta 3000
-    app.listen("127.0.0.1:3000")
+    run_app(|| async {
+        let _ = app.listen("127.0.0.1:3000").await;
+    });
 }
 
 ```
 Visite ```localhost:3000/username``` e vera o resultado ```O valor de name e: username```
 
-# Exemplo de Middleware
+# Exemplo de Fairing
 ``` rust
-pub struct Log;
+use milim_web::{
+    async_trait, context::Context, fairing::Fairing, request::HttpRequest,
+    response::HttpResponse,
+};
 
-impl Log {
-    fn new() -> Self {
-        Self
-    }
-}
+pub struct Log;
 
-impl Middleware for Log {
-    // Deve retornar true para passar para o proximo middleware ou proxima rota exemplo:
-    // M1 -> M2 -> Rota -> M2 -> M1
-    fn on_request(&self, req: &mut HttpRequest, ctx: &Context) -> MwFlow {
-        println!("request method: {:?}", req.method);
-        MwFlow::Continue
+#[async_trait]
+impl Fairing for Log {
+    // Executado antes dos guards e do handler da rota
+    async fn on_request(&self, req: &mut HttpRequest, _ctx: &Context) {
+        println!("request method: {:?}", req.raw.method);
     }
 
-    fn on_response(&self, req: &HttpRequest, res: &mut HttpResponse, ctx: &Context) {
-        println!("Response body {:?}", res.body);
+    // Executado depois do handler, pode alterar a resposta
+    async fn on_response(&self, _req: &HttpRequest, res: &mut HttpResponse, _ctx: &Context) {
+        println!("Response body {:?}", res.get_body_text());
     }
 }
 
 ```
-# Criando rota que usa esse Middleware
-
-``` rust
-app.route_use(Get, "/", mx![Log], |req, res, ctx| {
-    res.body("Hello World!!");
-});
-```
+# Registrando o Fairing
 
-Ou como middleware global:
 ```rust
-app.global_use(Log);
+# use milim_web::{async_trait, context::Context, fairing::Fairing, request::HttpRequest, response::HttpResponse};
+# pub struct Log;
+# #[async_trait]
+# impl Fairing for Log {
+#     async fn on_request(&self, _req: &mut HttpRequest, _ctx: &Context) {}
+#     async fn on_response(&self, _req: &HttpRequest, _res: &mut HttpResponse, _ctx: &Context) {}
+# }
+let mut app = milim_web::server();
+app.fairing(Log);
 
 ```
 ---
@@ -90,21 +89,37 @@ use tokio::runtime::Runtime;
 
 use crate::aplication::App;
 pub mod aplication;
+pub mod body;
 pub mod config;
 pub mod context;
+#[cfg(feature = "serde")]
+pub(crate) mod de;
 pub mod error;
 pub mod fairing;
 pub mod guard;
 pub mod handler;
+pub mod header;
+pub(crate) mod http2;
 pub mod prelude;
+pub mod query;
+pub(crate) mod reader;
 pub mod request;
 pub mod responder;
 pub mod response;
 pub mod router;
+pub mod server;
+pub mod shutdown;
+pub mod sse;
 pub mod status;
+pub mod testing;
+pub mod tls;
+pub(crate) mod tree;
+pub(crate) mod upgrade;
+pub mod websocket;
 pub use macros;
 
 pub use async_trait::async_trait;
+pub use bytes;
 
 pub fn server() -> App {
     App::new()
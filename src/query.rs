@@ -0,0 +1,189 @@
+use std::fmt;
+
+use crate::{aplication::percent_decode, error::ParseError};
+
+/// Parametros da query string, ja decodificados.
+///
+/// Uma chave pode aparecer varias vezes e a ordem da URL e mantida. Chaves no
+/// formato `filtro[campo]` podem ser agrupadas com `group`
+/// ``` rust
+/// use milim_web::query::QueryMap;
+///
+/// let query = QueryMap::parse("tag=a&tag=b&filter[status]=open&filter[owner]=ana").unwrap();
+/// assert_eq!(query.get("tag"), Some("a"));
+/// assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+///
+/// let filter = query.group("filter");
+/// assert_eq!(filter.get("status"), Some("open"));
+/// assert_eq!(filter.len(), 2);
+/// ```
+#[derive(Clone, Default, PartialEq)]
+pub struct QueryMap {
+    entries: Vec<(String, String)>,
+}
+
+impl QueryMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+    /// Faz o parse de uma query sem o `?`, `+` e decodificado como espaço
+    pub fn parse(query: &str) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        for pair in query.split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            entries.push((
+                percent_decode(key, true)?.into_owned(),
+                percent_decode(value, true)?.into_owned(),
+            ));
+        }
+        Ok(Self { entries })
+    }
+    /// Primeiro valor da chave
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    /// Todos os valores da chave na ordem da URL
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+    /// Chaves `name[campo]` com o primeiro nivel de colchetes removido,
+    /// `filter[a][b]` vira `a[b]` e `tag[]` vira a chave vazia
+    pub fn group(&self, name: &str) -> QueryMap {
+        let entries = self
+            .entries
+            .iter()
+            .filter_map(|(key, value)| {
+                let inner = key.strip_prefix(name)?.strip_prefix('[')?;
+                let (field, rest) = inner.split_once(']')?;
+                Some((format!("{}{}", field, rest), value.clone()))
+            })
+            .collect();
+        QueryMap { entries }
+    }
+    /// Percorre os pares chave e valor na ordem da URL
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Debug for QueryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for QueryMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let entries = iter
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_string(), v.as_ref().to_string()))
+            .collect();
+        QueryMap { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::QueryMap;
+    use crate::{
+        error::ParseError,
+        request::{HttpRequest, Method::*},
+        server,
+        testing::TestClient,
+    };
+
+    fn pairs(query: &QueryMap) -> Vec<(&str, &str)> {
+        query.iter().collect()
+    }
+
+    #[test]
+    fn keeps_url_order_and_repeated_keys() {
+        let query = QueryMap::parse("b=2&a=1&&b=3&flag&a+b=c%26d").unwrap();
+        assert_eq!(
+            pairs(&query),
+            [
+                ("b", "2"),
+                ("a", "1"),
+                ("b", "3"),
+                ("flag", ""),
+                ("a b", "c&d")
+            ]
+        );
+        assert_eq!(query.get("b"), Some("2"));
+        assert_eq!(query.get_all("b").collect::<Vec<_>>(), ["2", "3"]);
+        assert!(query.contains_key("flag"));
+        assert_eq!(query.get("missing"), None);
+        assert!(QueryMap::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn groups_bracket_keys_one_level_at_a_time() {
+        let query = QueryMap::parse(
+            "filter[status]=open&filters[x]=no&filter[owner][name]=ana&filter=bare&tag[]=a&tag[]=b&filter[status]=closed",
+        )
+        .unwrap();
+        let filter = query.group("filter");
+        assert_eq!(
+            pairs(&filter),
+            [
+                ("status", "open"),
+                ("owner[name]", "ana"),
+                ("status", "closed")
+            ]
+        );
+        assert_eq!(filter.group("owner").get("name"), Some("ana"));
+        assert_eq!(
+            query.group("tag").get_all("").collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert!(query.group("missing").is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        assert_eq!(
+            QueryMap::parse("a=%zz"),
+            Err(ParseError::InvalidEncoding("%zz".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn handlers_borrow_the_parsed_query() {
+        let mut app = server();
+        app.route(Get, "/items")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let query = req.raw.query_map();
+                let tags: Vec<_> = query.get_all("tag").collect();
+                format!("{:?} {:?}", tags, query.group("filter").get("status"))
+            });
+        let client = TestClient::new(app).await;
+
+        let res = client
+            .get("/items?tag=b&filter%5Bstatus%5D=open&tag=a")
+            .send()
+            .await;
+        assert_eq!(res.get_body_text(), r#"["b", "a"] Some("open")"#);
+    }
+}
@@ -0,0 +1,500 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    time::{Instant, timeout, timeout_at},
+};
+
+use crate::{
+    config::Config,
+    error::{ParseError, ServerError},
+    header::HeaderMap,
+    request::process_header_line,
+};
+
+/// Requisição lida da conexão
+#[derive(Debug)]
+pub(crate) struct RawRequest {
+    /// Head seguido do body, ja decodificado quando chunked
+    pub(crate) bytes: Vec<u8>,
+    /// Trailers de um body chunked, mantidos fora do head para não alterar
+    /// headers de framing ou roteamento, RFC 9110 6.5.1
+    pub(crate) trailers: HeaderMap,
+}
+
+/// Le requisições completas de uma conexão, guardando os bytes que sobram
+/// para a proxima requisição quando o cliente envia varias em sequencia
+pub(crate) struct RequestReader {
+    buf: Vec<u8>,
+    max_head: usize,
+    max_headers: usize,
+    max_body: usize,
+    read_timeout: Duration,
+}
+
+impl RequestReader {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_head: Config::get_kb_value(config.max_header_len_kb),
+            max_headers: config.max_headers as usize,
+            max_body: Config::get_kb_value(config.max_body_kb),
+            read_timeout: Duration::from_secs(config.read_timeout_s.into()),
+        }
+    }
+
+    /// Bytes ja lidos que ainda não fazem parte de uma requisição
+    pub(crate) fn into_buffer(self) -> Bytes {
+        Bytes::from(self.buf)
+    }
+
+    /// Espera o primeiro byte de uma nova requisição, `idle` limita a espera.
+    ///
+    /// Retorna `false` se a conexão for fechada ou ficar ociosa antes de uma
+    /// nova requisição começar. Pode ser cancelado sem perder bytes lidos
+    pub(crate) async fn wait_request<S>(
+        &mut self,
+        stream: &mut S,
+        idle: Duration,
+    ) -> Result<bool, ServerError>
+    where
+        S: AsyncRead + Unpin,
+    {
+        if !self.buf.is_empty() {
+            return Ok(true);
+        }
+        match timeout(idle, self.fill(stream)).await {
+            Ok(read) => Ok(read? > 0),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Le o head ate `\r\n\r\n` e depois exatamente `Content-Length` bytes de body
+    pub(crate) async fn next_request<S>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<RawRequest, ServerError>
+    where
+        S: AsyncRead + Unpin,
+    {
+        // O restante da requisição deve chegar dentro de read_timeout_s
+        let deadline = Instant::now() + self.read_timeout;
+
+        let head_end = loop {
+            if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if self.buf.len() > self.max_head {
+                return Err(ServerError::HeaderTooLarge);
+            }
+            self.fill_until(stream, deadline).await?;
+        };
+        if head_end > self.max_head {
+            return Err(ServerError::HeaderTooLarge);
+        }
+
+        let head = &self.buf[..head_end];
+        // A primeira linha e a linha da requisição
+        if head
+            .split(|b| *b == b'\n')
+            .skip(1)
+            .filter(|l| l.len() > 1)
+            .count()
+            > self.max_headers
+        {
+            return Err(ServerError::HeaderTooLarge);
+        }
+        if is_chunked(head)? {
+            // Com os dois headers o tamanho do body fica ambiguo, RFC 9112 6.1
+            if !header_values(head, "content-length").is_empty() {
+                return Err(ServerError::InvalidTransferEncoding);
+            }
+            return self.read_chunked(stream, head_end, deadline).await;
+        }
+        let length = content_length(head)?;
+        if length > self.max_body {
+            return Err(ServerError::PayloadTooLarge);
+        }
+
+        while self.buf.len() < head_end + length {
+            self.fill_until(stream, deadline).await?;
+        }
+        Ok(RawRequest {
+            bytes: self.buf.drain(..head_end + length).collect(),
+            trailers: HeaderMap::new(),
+        })
+    }
+
+    /// Decodifica um body `Transfer-Encoding: chunked`.
+    ///
+    /// Retorna o head seguido do body decodificado. No head o Transfer-Encoding
+    /// e trocado pelo Content-Length do body e os trailers ficam separados
+    async fn read_chunked<S>(
+        &mut self,
+        stream: &mut S,
+        head_end: usize,
+        deadline: Instant,
+    ) -> Result<RawRequest, ServerError>
+    where
+        S: AsyncRead + Unpin,
+    {
+        let mut body = Vec::new();
+        let mut trailers = HeaderMap::new();
+        let mut pos = head_end;
+
+        // Chunks: `<tamanho em hex>[;extensões]\r\n<dados>\r\n` ate um chunk de tamanho 0
+        loop {
+            let line_end = self.line_end(stream, pos, deadline).await?;
+            let line = String::from_utf8_lossy(&self.buf[pos..line_end]);
+            let size = line.split(';').next().unwrap_or("").trim();
+            // `from_str_radix` aceitaria um `+` no inicio
+            if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ServerError::InvalidTransferEncoding);
+            }
+            let size = usize::from_str_radix(size, 16).map_err(|_| ServerError::PayloadTooLarge)?;
+            pos = line_end + 2;
+            if size == 0 {
+                break;
+            }
+            // Comparado sem somar, o tamanho vem do cliente e pode ser enorme.
+            // Depois dessa verificação `pos + size + 2` fica limitado por max_body
+            if size > self.max_body - body.len() {
+                return Err(ServerError::PayloadTooLarge);
+            }
+            while self.buf.len() < pos + size + 2 {
+                self.fill_until(stream, deadline).await?;
+            }
+            if &self.buf[pos + size..pos + size + 2] != b"\r\n" {
+                return Err(ServerError::InvalidTransferEncoding);
+            }
+            body.extend_from_slice(&self.buf[pos..pos + size]);
+            pos += size + 2;
+        }
+
+        // Trailers ate a linha vazia
+        loop {
+            let line_end = self.line_end(stream, pos, deadline).await?;
+            if line_end == pos {
+                pos += 2;
+                break;
+            }
+            if line_end - head_end > self.max_head {
+                return Err(ServerError::HeaderTooLarge);
+            }
+            let line = std::str::from_utf8(&self.buf[pos..line_end])
+                .map_err(|_| ParseError::InvalidUtf8)?;
+            let (key, value) = process_header_line(line)?;
+            trailers.append(key, value);
+            pos = line_end + 2;
+        }
+
+        let mut bytes = Vec::with_capacity(head_end + body.len());
+        for line in self.buf[..head_end - 2].split_inclusive(|b| *b == b'\n') {
+            if !header_values(line, "transfer-encoding").is_empty() {
+                continue;
+            }
+            bytes.extend_from_slice(line);
+        }
+        bytes.extend_from_slice(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes());
+        bytes.extend_from_slice(&body);
+        self.buf.drain(..pos);
+        Ok(RawRequest { bytes, trailers })
+    }
+
+    /// Le ate encontrar `\r\n` a partir de `from`, retorna a posição do `\r`
+    async fn line_end<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+        from: usize,
+        deadline: Instant,
+    ) -> Result<usize, ServerError> {
+        loop {
+            if let Some(pos) = find(&self.buf[from..], b"\r\n") {
+                return Ok(from + pos);
+            }
+            if self.buf.len() - from > self.max_head {
+                return Err(ServerError::InvalidTransferEncoding);
+            }
+            self.fill_until(stream, deadline).await?;
+        }
+    }
+
+    async fn fill<S: AsyncRead + Unpin>(&mut self, stream: &mut S) -> Result<usize, ServerError> {
+        let mut chunk = [0u8; 8192];
+        let n = stream.read(&mut chunk).await?;
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(n)
+    }
+
+    async fn fill_until<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+        deadline: Instant,
+    ) -> Result<(), ServerError> {
+        match timeout_at(deadline, self.fill(stream)).await {
+            Ok(Ok(0)) => Err(ServerError::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            Ok(read) => read.map(|_| ()),
+            Err(_) => Err(ServerError::Timeout),
+        }
+    }
+}
+
+/// Procura a posição de `needle` em `haystack`
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Obtem os valores de um header diretamente dos bytes do head
+fn header_values(head: &[u8], name: &str) -> Vec<String> {
+    head.split(|b| *b == b'\n')
+        .filter_map(|line| {
+            let line = String::from_utf8_lossy(line);
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().to_string())
+        })
+        .collect()
+}
+
+/// Obtem o valor do header Content-Length, 0 quando ausente.
+///
+/// Repetições, em headers separados ou em lista, so são aceitas com o mesmo valor
+fn content_length(head: &[u8]) -> Result<usize, ServerError> {
+    let values = header_values(head, "content-length");
+    let mut length = None;
+    for value in values.iter().flat_map(|v| v.split(',')) {
+        let value = value.trim();
+        // `parse` aceitaria um `+` no inicio
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ServerError::InvalidContentLength);
+        }
+        let value: usize = value
+            .parse()
+            .map_err(|_| ServerError::InvalidContentLength)?;
+        if length.is_some_and(|length| length != value) {
+            return Err(ServerError::InvalidContentLength);
+        }
+        length = Some(value);
+    }
+    Ok(length.unwrap_or(0))
+}
+
+/// Verifica se o body usa `Transfer-Encoding: chunked`,
+/// qualquer outra codificação final e rejeitada
+fn is_chunked(head: &[u8]) -> Result<bool, ServerError> {
+    let codings = header_values(head, "transfer-encoding");
+    let Some(last) = codings.iter().flat_map(|v| v.split(',')).last() else {
+        return Ok(false);
+    };
+    if last.trim().eq_ignore_ascii_case("chunked") {
+        Ok(true)
+    } else {
+        Err(ServerError::InvalidTransferEncoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncWriteExt, duplex};
+
+    use super::RequestReader;
+    use crate::{config::Config, error::ServerError, request::HttpRequestData};
+
+    fn reader(configure: impl FnOnce(&mut Config)) -> RequestReader {
+        let mut config = Config::new();
+        configure(&mut config);
+        RequestReader::new(&config)
+    }
+
+    #[tokio::test]
+    async fn reads_body_by_content_length_and_keeps_next_request() {
+        let mut stream: &[u8] =
+            b"POST /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloGET /b HTTP/1.1\r\n\r\n";
+        let mut reader = reader(|_| {});
+
+        let first = reader.next_request(&mut stream).await.unwrap().bytes;
+        assert_eq!(first, b"POST /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello");
+        let second = reader.next_request(&mut stream).await.unwrap().bytes;
+        assert_eq!(second, b"GET /b HTTP/1.1\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn accepts_repeated_equal_content_length() {
+        let mut stream: &[u8] =
+            b"POST / HTTP/1.1\r\nContent-Length: 2, 2\r\nContent-Length: 2\r\n\r\nhi";
+        let raw = reader(|_| {})
+            .next_request(&mut stream)
+            .await
+            .unwrap()
+            .bytes;
+        assert!(raw.ends_with(b"\r\n\r\nhi"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_content_length() {
+        let heads: [&[u8]; 5] = [
+            b"POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 5\r\n\r\nhello",
+            b"POST / HTTP/1.1\r\nContent-Length: 2, 5\r\n\r\nhello",
+            b"POST / HTTP/1.1\r\nContent-Length: +5\r\n\r\nhello",
+            b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: \r\n\r\n",
+        ];
+        for mut stream in heads {
+            let result = reader(|_| {}).next_request(&mut stream).await;
+            assert!(
+                matches!(result, Err(ServerError::InvalidContentLength)),
+                "{:?}",
+                String::from_utf8_lossy(stream)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_body_over_max_body() {
+        let mut stream: &[u8] = b"POST / HTTP/1.1\r\nContent-Length: 1025\r\n\r\n";
+        let result = reader(|c| c.max_body_kb = 1)
+            .next_request(&mut stream)
+            .await;
+        assert!(matches!(result, Err(ServerError::PayloadTooLarge)));
+    }
+
+    #[tokio::test]
+    async fn rejects_head_over_limits() {
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(2048));
+        let result = reader(|c| c.max_header_len_kb = 1)
+            .next_request(&mut long.as_bytes())
+            .await;
+        assert!(matches!(result, Err(ServerError::HeaderTooLarge)));
+
+        let mut many: &[u8] = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n";
+        let result = reader(|c| c.max_headers = 2).next_request(&mut many).await;
+        assert!(matches!(result, Err(ServerError::HeaderTooLarge)));
+    }
+
+    #[tokio::test]
+    async fn times_out_on_incomplete_request() {
+        let (mut client, mut server) = duplex(1024);
+        client
+            .write_all(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhe")
+            .await
+            .unwrap();
+        let result = reader(|c| c.read_timeout_s = 0)
+            .next_request(&mut server)
+            .await;
+        assert!(matches!(result, Err(ServerError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn wait_request_gives_up_on_silent_connection() {
+        let (_client, mut server) = duplex(1024);
+        let mut reader = reader(|_| {});
+        let ready = reader
+            .wait_request(&mut server, Duration::from_millis(10))
+            .await;
+        assert!(matches!(ready, Ok(false)));
+    }
+
+    #[tokio::test]
+    async fn reports_eof_before_complete_request() {
+        let mut stream: &[u8] = b"GET / HTTP/1.1\r\n";
+        let result = reader(|_| {}).next_request(&mut stream).await;
+        assert!(matches!(result, Err(ServerError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn decodes_chunked_body_with_trailers() {
+        let mut stream: &[u8] = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 11\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+        let mut reader = reader(|_| {});
+
+        let raw = reader.next_request(&mut stream).await.unwrap();
+        assert_eq!(
+            raw.bytes,
+            b"POST / HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world"
+        );
+        assert_eq!(raw.trailers.get("x-sum"), Some("11"));
+        let next = reader.next_request(&mut stream).await.unwrap().bytes;
+        assert_eq!(next, b"GET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn trailers_do_not_override_head() {
+        let mut stream: &[u8] =
+            b"POST / HTTP/1.1\r\nHost: app\r\nTransfer-Encoding: chunked\r\n\r\n\
+            2\r\nhi\r\n0\r\nHost: evil\r\nContent-Length: 99\r\n\r\n";
+        let raw = reader(|_| {}).next_request(&mut stream).await.unwrap();
+        let req = HttpRequestData::parse(&raw.bytes).unwrap();
+        assert_eq!(req.headers.get_all("host").collect::<Vec<_>>(), ["app"]);
+        assert_eq!(
+            req.headers.get_all("content-length").collect::<Vec<_>>(),
+            ["2"]
+        );
+        assert!(!req.headers.contains_key("transfer-encoding"));
+        assert_eq!(req.body, "hi");
+        assert_eq!(raw.trailers.get("host"), Some("evil"));
+        assert_eq!(raw.trailers.get("content-length"), Some("99"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_trailer() {
+        let mut stream: &[u8] =
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nBad Trailer\r\n\r\n";
+        let result = reader(|_| {}).next_request(&mut stream).await;
+        assert!(matches!(result, Err(ServerError::Parse(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_chunk_sizes_over_max_body() {
+        let bodies: [&[u8]; 3] = [
+            b"1\r\na\r\nffffffffffffffff\r\n",
+            b"1\r\na\r\n10000000000000000\r\n",
+            b"401\r\n",
+        ];
+        for body in bodies {
+            let mut raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+            raw.extend_from_slice(body);
+            let result = reader(|c| c.max_body_kb = 1)
+                .next_request(&mut raw.as_slice())
+                .await;
+            assert!(matches!(result, Err(ServerError::PayloadTooLarge)));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_chunked_body() {
+        let bodies: [&[u8]; 4] = [
+            b"+2\r\nhi\r\n0\r\n\r\n",
+            b"zz\r\nhi\r\n0\r\n\r\n",
+            b"\r\n",
+            b"2\r\nhiX\r\n0\r\n\r\n",
+        ];
+        for body in bodies {
+            let mut raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+            raw.extend_from_slice(body);
+            let result = reader(|_| {}).next_request(&mut raw.as_slice()).await;
+            assert!(matches!(result, Err(ServerError::InvalidTransferEncoding)));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_chunked_with_content_length() {
+        let mut stream: &[u8] = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\
+            Content-Length: 3\r\n\r\n2\r\nhi\r\n0\r\n\r\n";
+        let result = reader(|_| {}).next_request(&mut stream).await;
+        assert!(matches!(result, Err(ServerError::InvalidTransferEncoding)));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_transfer_coding() {
+        let mut stream: &[u8] = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n";
+        let result = reader(|_| {}).next_request(&mut stream).await;
+        assert!(matches!(result, Err(ServerError::InvalidTransferEncoding)));
+    }
+}
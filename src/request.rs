@@ -1,20 +1,129 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, net::SocketAddr, str::FromStr};
 
-use crate::context::RequestContext;
+use bytes::Bytes;
+
+#[cfg(feature = "serde")]
+use crate::error::DeserializeError;
+use crate::{
+    context::RequestContext,
+    error::{ParamError, ParseError},
+    header::HeaderMap,
+    query::QueryMap,
+    reader::find,
+};
 
 #[derive(Debug)]
 pub struct HttpRequest {
     pub raw: HttpRequestData,
     pub ctx: RequestContext,
+    pub conn: ConnectionInfo,
 }
 
 impl HttpRequest {
     pub fn new(req: HttpRequestData) -> Self {
+        Self::with_connection(req, ConnectionInfo::default())
+    }
+    pub fn with_connection(req: HttpRequestData, conn: ConnectionInfo) -> Self {
         Self {
             raw: req,
             ctx: RequestContext::new(),
+            conn,
+        }
+    }
+    /// Indica se a requisição chegou por uma conexão TLS
+    pub fn is_secure(&self) -> bool {
+        self.conn.secure
+    }
+    /// Protocolo negociado via ALPN, como `b"http/1.1"`
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.conn.alpn.as_deref()
+    }
+    /// Endereço do cliente
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.conn.peer_addr
+    }
+    /// Deserializa a query em `T`, chaves repetidas preenchem campos `Vec`
+    /// ``` rust
+    /// use std::sync::Arc;
+    ///
+    /// use milim_web::{request::{HttpRequest, Method::*}, responder::Responder, server};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Page {
+    ///     page: u32,
+    ///     tag: Vec<String>,
+    /// }
+    ///
+    /// let mut app = server();
+    /// app.route(Get, "/posts").handler(|req: Arc<HttpRequest>, _ctx| async move {
+    ///     let res: Box<dyn Responder + Send> = match req.query::<Page>() {
+    ///         Ok(page) => Box::new(format!("Pagina {} {:?}", page.page, page.tag)),
+    ///         Err(e) => Box::new(e),
+    ///     };
+    ///     res
+    /// });
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn query<'de, T: serde::Deserialize<'de>>(&'de self) -> Result<T, DeserializeError> {
+        let mut pairs: Vec<(&str, Vec<&str>)> = Vec::new();
+        for (key, value) in self.raw.queryes.iter() {
+            match pairs.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, values)) => values.push(value),
+                None => pairs.push((key, vec![value])),
+            }
         }
+        crate::de::from_pairs(pairs)
+    }
+    /// Deserializa os parametros da rota em `T`
+    #[cfg(feature = "serde")]
+    pub fn params<'de, T: serde::Deserialize<'de>>(&'de self) -> Result<T, DeserializeError> {
+        let pairs = self
+            .raw
+            .params
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), vec![value.as_str()]))
+            .collect();
+        crate::de::from_pairs(pairs)
     }
+    /// Parametro da rota convertido para `T`
+    /// ``` rust
+    /// use std::sync::Arc;
+    ///
+    /// use milim_web::{request::{HttpRequest, Method::*}, run_app, server, testing::TestClient};
+    ///
+    /// let mut app = server();
+    /// app.route(Get, "/users/:id<u64>").handler(|req: Arc<HttpRequest>, _ctx| async move {
+    ///     let id: u64 = req.param("id").unwrap();
+    ///     format!("Usuario {}", id + 1)
+    /// });
+    ///
+    /// run_app(|| async {
+    ///     let client = TestClient::new(app).await;
+    ///     assert_eq!(client.get("/users/41").send().await.get_body_text(), "Usuario 42");
+    /// });
+    /// ```
+    pub fn param<T: FromStr>(&self, name: &str) -> Result<T, ParamError> {
+        let value = self
+            .raw
+            .params
+            .as_ref()
+            .and_then(|params| params.get(name))
+            .ok_or_else(|| ParamError::Missing(name.to_string()))?;
+        value.parse().map_err(|_| ParamError::Invalid {
+            name: name.to_string(),
+            value: value.clone(),
+        })
+    }
+}
+
+/// Informações da conexão em que a requisição foi recebida
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionInfo {
+    pub peer_addr: Option<SocketAddr>,
+    pub secure: bool,
+    pub alpn: Option<Vec<u8>>,
 }
 // Enum que representa o metodo da requisição http
 #[derive(Debug, PartialEq, Clone)]
@@ -52,6 +161,7 @@ impl From<&str> for Method {
 /// Enum representando a versão no header da requisição http
 #[derive(Debug, PartialEq)]
 pub enum Version {
+    V1_0,
     V1_1,
     V2_0,
     Uninitialized,
@@ -61,6 +171,7 @@ pub enum Version {
 impl From<&str> for Version {
     fn from(value: &str) -> Self {
         match value {
+            "HTTP/1.0" => Version::V1_0,
             "HTTP/1.1" => Version::V1_1,
             "HTTP/2.0" => Version::V2_0,
             _ => Version::Uninitialized,
@@ -80,81 +191,230 @@ pub struct HttpRequestData {
     pub method: Method,
     pub version: Version,
     pub resource: Resource,
-    pub headers: HashMap<String, String>,
-    pub body: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub(crate) trailers: HeaderMap,
     pub(crate) params: Option<HashMap<String, String>>,
-    pub(crate) queryes: Option<HashMap<String, String>>,
+    pub(crate) queryes: QueryMap,
 }
 
 impl HttpRequestData {
     pub fn get_param(&self, key: &str) -> Option<String> {
-        self.params.clone()?.get(key).cloned()
+        self.params.as_ref()?.get(key).cloned()
     }
+    /// Primeiro valor do parametro da query
     pub fn get_query(&self, key: &str) -> Option<String> {
-        self.queryes.clone()?.get(key).cloned()
+        self.queryes.get(key).map(str::to_string)
+    }
+    /// Todos os parametros da query, vazio antes do roteamento
+    pub fn query_map(&self) -> &QueryMap {
+        &self.queryes
+    }
+    /// Body como texto, sequencias UTF-8 invalidas são substituidas
+    pub fn get_body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+    /// Trailers enviados depois de um body chunked, não fazem parte de `headers`
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+    /// Indica se a conexão deve continuar aberta apos a resposta,
+    /// HTTP/1.1 e persistente por padrão e HTTP/1.0 apenas com `Connection: keep-alive`
+    pub fn keep_alive(&self) -> bool {
+        let has_token = |token: &str| {
+            self.headers
+                .get_all("Connection")
+                .flat_map(|v| v.split(','))
+                .any(|v| v.trim().eq_ignore_ascii_case(token))
+        };
+        match self.version {
+            Version::V1_0 => has_token("keep-alive"),
+            _ => !has_token("close"),
+        }
     }
 }
-/// Implementa From<String> para
-impl From<String> for HttpRequestData {
-    fn from(req: String) -> Self {
-        // Variaveis que armazenam os campos da requisição
-        let mut parsed_method = Method::Uninitialized;
-        let mut parsed_version = Version::V1_1;
-        let mut parsed_resource = Resource::Path("".to_string());
-        let mut parsed_headers = HashMap::new();
-
+impl HttpRequestData {
+    /// Faz o parse de uma requisição completa, head seguido do body.
+    ///
+    /// O head deve ser UTF-8 valido, a linha da requisição deve ter metodo, caminho
+    /// e versão separados por espaço e cada header deve ter o formato `nome: valor`
+    pub fn parse(raw: &[u8]) -> Result<Self, ParseError> {
         // Separa o head do body da requisição
-        let parts: Vec<&str> = req.splitn(2, "\r\n\r\n").collect();
-        let head = parts.get(0).unwrap_or(&"");
-        let body_part = parts.get(1).unwrap_or(&"");
-
-        for line in head.lines() {
-            if line.contains("HTTP") {
-                let (method, resource, version) = process_req_line(line);
-                parsed_method = method;
-                parsed_version = version;
-                parsed_resource = resource;
-            } else if line.contains(":") {
-                let (key, value) = process_header_line(line);
-                parsed_headers.insert(key, value);
-            }
+        let (head, body) = match find(raw, b"\r\n\r\n") {
+            Some(pos) => (&raw[..pos], &raw[pos + 4..]),
+            None => (raw, &[][..]),
+        };
+        let head = std::str::from_utf8(head).map_err(|_| ParseError::InvalidUtf8)?;
+        let mut lines = head.split('\n').map(|line| line.trim_end_matches('\r'));
+
+        let (method, resource, version) = process_req_line(lines.next().unwrap_or(""))?;
+        let mut headers = HeaderMap::new();
+        for line in lines {
+            let (key, value) = process_header_line(line)?;
+            headers.append(key, value);
         }
 
-        HttpRequestData {
-            method: parsed_method,
-            version: parsed_version,
-            resource: parsed_resource,
-            headers: parsed_headers,
-            body: body_part.to_string(),
+        Ok(HttpRequestData {
+            method,
+            version,
+            resource,
+            headers,
+            body: Bytes::copy_from_slice(body),
+            trailers: HeaderMap::new(),
             params: None,
-            queryes: None,
-        }
+            queryes: QueryMap::new(),
+        })
+    }
+}
+
+impl TryFrom<String> for HttpRequestData {
+    type Error = ParseError;
+
+    fn try_from(req: String) -> Result<Self, Self::Error> {
+        HttpRequestData::parse(req.as_bytes())
     }
 }
 
 /// Processa a primeira linha da requisição
-fn process_req_line(s: &str) -> (Method, Resource, Version) {
-    let mut words = s.split_whitespace();
-    let method = words.next().unwrap();
-    let resource = words.next().unwrap();
-    let version = words.next().unwrap();
-    (
-        method.into(),
+fn process_req_line(s: &str) -> Result<(Method, Resource, Version), ParseError> {
+    let mut words = s.split(' ');
+    let (Some(method), Some(resource)) = (words.next(), words.next()) else {
+        return Err(ParseError::InvalidRequestLine);
+    };
+    if method.is_empty() || resource.is_empty() {
+        return Err(ParseError::InvalidRequestLine);
+    }
+    let version = match words.next() {
+        Some(version) if !version.is_empty() => version,
+        _ => return Err(ParseError::MissingVersion),
+    };
+    if words.next().is_some() {
+        return Err(ParseError::InvalidRequestLine);
+    }
+
+    let parsed_method = Method::from(method);
+    if parsed_method == Method::Uninitialized || method != method.to_uppercase() {
+        return Err(ParseError::InvalidMethod(method.to_string()));
+    }
+    let parsed_version = Version::from(version);
+    if parsed_version == Version::Uninitialized {
+        return Err(if version.starts_with("HTTP/") {
+            ParseError::UnsupportedVersion(version.to_string())
+        } else {
+            ParseError::MissingVersion
+        });
+    }
+    Ok((
+        parsed_method,
         Resource::Path(resource.into()),
-        version.into(),
-    )
+        parsed_version,
+    ))
+}
+
+/// Processa os headers da requisição, o valor vai ate o fim da linha
+pub(crate) fn process_header_line(s: &str) -> Result<(&str, &str), ParseError> {
+    match s.split_once(':') {
+        Some((key, value)) if !key.is_empty() && !key.contains(|c: char| c.is_whitespace()) => {
+            Ok((key, value.trim()))
+        }
+        _ => Err(ParseError::InvalidHeader(s.to_string())),
+    }
 }
 
-/// Processa os headers da requisição
-fn process_header_line(s: &str) -> (String, String) {
-    let mut header_items = s.split(":");
-    let mut key = String::from("");
-    let mut value = String::from("");
-    if let Some(k) = header_items.next() {
-        key = k.to_string()
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{HttpRequest, HttpRequestData, Method, Method::*, Resource, Version};
+    use crate::{error::ParseError, server, status::StatusCode, testing::TestClient};
+
+    #[test]
+    fn parses_head_and_body() {
+        let req = HttpRequestData::parse(
+            b"POST /items?x=1 HTTP/1.0\r\nHost: a\r\nX-Tag: 1\r\nx-tag: 2\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.version, Version::V1_0);
+        assert_eq!(req.resource, Resource::Path("/items?x=1".to_string()));
+        assert_eq!(req.headers.get_all("x-tag").collect::<Vec<_>>(), ["1", "2"]);
+        assert_eq!(req.body.as_ref(), b"body");
+    }
+
+    #[test]
+    fn header_values_keep_colons() {
+        let req = HttpRequestData::parse(
+            b"GET / HTTP/1.1\r\nHost: localhost:3000\r\nX-Time:  12:30:00 \r\nX-Empty:\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(req.headers.get("host"), Some("localhost:3000"));
+        assert_eq!(req.headers.get("x-time"), Some("12:30:00"));
+        assert_eq!(req.headers.get("x-empty"), Some(""));
+    }
+
+    #[test]
+    fn rejects_malformed_heads() {
+        let cases: [(&[u8], ParseError); 7] = [
+            (b"\xff / HTTP/1.1\r\n\r\n", ParseError::InvalidUtf8),
+            (b"GET\r\n\r\n", ParseError::InvalidRequestLine),
+            (
+                b"GET / HTTP/1.1 extra\r\n\r\n",
+                ParseError::InvalidRequestLine,
+            ),
+            (b"GET /\r\n\r\n", ParseError::MissingVersion),
+            (
+                b"FETCH / HTTP/1.1\r\n\r\n",
+                ParseError::InvalidMethod("FETCH".into()),
+            ),
+            (
+                b"get / HTTP/1.1\r\n\r\n",
+                ParseError::InvalidMethod("get".into()),
+            ),
+            (
+                b"GET / HTTP/1.1\r\nBad Header: x\r\n\r\n",
+                ParseError::InvalidHeader("Bad Header: x".into()),
+            ),
+        ];
+        for (raw, expected) in cases {
+            let err = HttpRequestData::parse(raw).unwrap_err();
+            assert_eq!(err, expected);
+            assert_eq!(err.status_code(), StatusCode::BadRequest);
+        }
     }
-    if let Some(v) = header_items.next() {
-        value = v.trim().to_string()
+
+    #[test]
+    fn rejects_unsupported_version_with_505() {
+        let err = HttpRequestData::parse(b"GET / HTTP/3.0\r\n\r\n").unwrap_err();
+        assert_eq!(err, ParseError::UnsupportedVersion("HTTP/3.0".into()));
+        assert_eq!(err.status_code(), StatusCode::HttpVersionNotSupported);
+    }
+
+    #[tokio::test]
+    async fn answers_parse_errors_through_test_client() {
+        let client = TestClient::new(server()).await;
+        let res = client.send_raw(b"GET / HTTP/9.9\r\n\r\n").await;
+        assert_eq!(res.status(), &StatusCode::HttpVersionNotSupported);
+        let res = client.send_raw(b"GET / HTTP/1.1\r\nno-colon\r\n\r\n").await;
+        assert_eq!(res.status(), &StatusCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn converts_route_params() {
+        let mut app = server();
+        app.route(Get, "/users/:id")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let id: Result<u64, _> = req.param("id");
+                let missing: Result<u64, _> = req.param("name");
+                format!("{:?} {:?}", id, missing)
+            });
+        let client = TestClient::new(app).await;
+
+        let res = client.get("/users/42").send().await;
+        assert_eq!(res.get_body_text(), r#"Ok(42) Err(Missing("name"))"#);
+        let res = client.get("/users/abc").send().await;
+        assert_eq!(
+            res.get_body_text(),
+            r#"Err(Invalid { name: "id", value: "abc" }) Err(Missing("name"))"#
+        );
     }
-    (key, value)
 }
@@ -1,4 +1,15 @@
-use crate::{response::HttpResponse, status::StatusCode};
+use bytes::Bytes;
+
+use crate::{
+    body::Body, header::HeaderMap, response::HttpResponse, router::IntoBody, status::StatusCode,
+};
+
+/// Resposta com `Content-Type: application/octet-stream`
+fn binary_response<B: IntoBody>(body: B) -> HttpResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", "application/octet-stream");
+    HttpResponse::new(StatusCode::Ok, Some(headers), body)
+}
 
 pub trait Responder: Send {
     fn into_response(self: Box<Self>) -> HttpResponse;
@@ -14,13 +25,82 @@ impl Responder for String {
         HttpResponse::new(StatusCode::Ok, None, *self)
     }
 }
+impl Responder for Vec<u8> {
+    fn into_response(self: Box<Self>) -> HttpResponse {
+        binary_response(*self)
+    }
+}
+impl Responder for Bytes {
+    fn into_response(self: Box<Self>) -> HttpResponse {
+        binary_response(*self)
+    }
+}
+/// Body em stream enviado como `application/octet-stream`
+impl Responder for Body {
+    fn into_response(self: Box<Self>) -> HttpResponse {
+        let mut res = binary_response(Bytes::new());
+        res.set_body(*self);
+        res
+    }
+}
 impl Responder for HttpResponse {
     fn into_response(self: Box<Self>) -> HttpResponse {
         *self
     }
 }
+/// Responde `400 Bad Request` com o campo e o motivo no body
+#[cfg(feature = "serde")]
+impl Responder for crate::error::DeserializeError {
+    fn into_response(self: Box<Self>) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "text/plain; charset=utf-8");
+        HttpResponse::new(self.status_code(), Some(headers), self.to_string())
+    }
+}
+
 impl Responder for Box<dyn Responder + Send> {
     fn into_response(self: Box<Self>) -> HttpResponse {
         (*self).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use crate::{
+        request::{HttpRequest, Method::*},
+        server,
+        testing::TestClient,
+    };
+
+    #[tokio::test]
+    async fn binary_bodies_round_trip_byte_for_byte() {
+        let mut app = server();
+        app.route(Post, "/vec")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move { req.raw.body.to_vec() });
+        app.route(Post, "/bytes")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move { req.raw.body.clone() });
+        let client = TestClient::new(app).await;
+
+        let payload: &[u8] = &[0xff, 0x00, 0xfe];
+        for path in ["/vec", "/bytes"] {
+            let res = client.post(path).body(payload).send().await;
+            assert_eq!(res.get_body(), payload, "{}", path);
+            assert_eq!(res.header("Content-Type"), Some("application/octet-stream"));
+        }
+        let res = client
+            .post("/bytes")
+            .body(Bytes::from_static(b"\x89PNG\r\n\x1a\n"))
+            .send()
+            .await;
+        assert_eq!(res.get_body(), b"\x89PNG\r\n\x1a\n");
+        // O parse do head não altera um body que não e UTF-8
+        let res = client
+            .send_raw(b"POST /vec HTTP/1.1\r\nContent-Length: 3\r\n\r\n\xff\x00\xfe")
+            .await;
+        assert_eq!(res.get_body(), payload);
+    }
+}
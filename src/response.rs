@@ -1,14 +1,20 @@
-use std::{collections::HashMap, io::Result};
+use std::{borrow::Cow, io::Result};
 
-use crate::{router::IntoBody, status::StatusCode};
+use futures::StreamExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-#[derive(Clone, Debug, PartialEq)]
+use crate::{
+    body::Body, header::HeaderMap, router::IntoBody, status::StatusCode, upgrade::OnUpgrade,
+};
+
+#[derive(Debug, PartialEq)]
 pub struct HttpResponse {
     version: String,
     status_code: StatusCode,
     status_text: String,
-    headers: Option<HashMap<String, String>>,
-    body: String,
+    headers: HeaderMap,
+    body: Body,
+    upgrade: Option<OnUpgrade>,
 }
 
 impl Default for HttpResponse {
@@ -17,96 +23,314 @@ impl Default for HttpResponse {
             version: "HTTP/1.1".to_string(),
             status_code: StatusCode::Ok,
             status_text: "OK".to_string(),
-            headers: None,
-            body: "".to_string(),
+            headers: HeaderMap::new(),
+            body: Body::default(),
+            upgrade: None,
         }
     }
 }
 
 impl HttpResponse {
-    pub fn new(
-        status_code: StatusCode,
-        headers: Option<HashMap<String, String>>,
-        body: impl IntoBody,
-    ) -> Self {
+    pub fn new(status_code: StatusCode, headers: Option<HeaderMap>, body: impl IntoBody) -> Self {
         let mut response: HttpResponse = HttpResponse::default();
-        if status_code != StatusCode::Ok {
-            response.status_code = status_code.into();
-        }
-        response.headers = match &headers {
-            Some(_) => headers,
-            None => {
-                let mut h = HashMap::new();
-                h.insert("Content-Type".to_string(), "text/html".to_string());
-                Some(h)
-            }
-        };
-        response.status_text = response.get_status_code().to_string();
-        response.body = body.into_body();
+        response.set_status(status_code);
+        response.headers = headers.unwrap_or_else(|| {
+            let mut h = HeaderMap::new();
+            h.insert("Content-Type", "text/html");
+            h
+        });
+        response.body = Body::Full(body.into_body());
         response
     }
 
     pub fn get_version(&self) -> &str {
         &self.version
     }
-    pub fn get_status_code(&self) -> &str {
-        match self.status_code {
-            StatusCode::Ok => "200",
-            StatusCode::NotAutorized => "Not Autorized",
-            StatusCode::BadRequest => "Bad Request",
-            StatusCode::NotFound => "Not Found",
-            StatusCode::MethodNotAllowed => "Method Not Allowed",
-            StatusCode::InternalServerError => "Internal Server Error",
-        }
+    /// Codigo numerico do status, usado na linha de status
+    pub fn get_status_code(&self) -> u16 {
+        self.status_code.as_u16()
+    }
+    pub fn status(&self) -> &StatusCode {
+        &self.status_code
+    }
+    /// Altera o status e a frase da linha de status
+    pub fn set_status(&mut self, status_code: StatusCode) {
+        self.status_text = status_code.canonical_reason().unwrap_or("").to_string();
+        self.status_code = status_code;
     }
     pub fn get_status_text(&self) -> &str {
         &self.status_text
     }
     pub fn get_headers(&self) -> String {
-        let map: HashMap<String, String> = self.headers.clone().expect("Erro ao obter headers");
         let mut header_string: String = "".into();
-        for (k, v) in map.iter() {
+        for (k, v) in self.headers.iter() {
             header_string = format!("{}{}:{}\r\n", header_string, k, v);
         }
         header_string
     }
-    pub fn get_body(&self) -> &str {
-        &self.body
+    /// Obtem o valor de um header, ignorando maiusculas e minusculas no nome
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers.get(key)
+    }
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+    /// Body em memoria, vazio quando o body e um stream ainda não lido com `read_body`
+    pub fn get_body(&self) -> &[u8] {
+        match &self.body {
+            Body::Full(bytes) => bytes,
+            Body::Stream(_) => &[],
+        }
+    }
+    /// Body como texto, sequencias UTF-8 invalidas são substituidas
+    pub fn get_body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.get_body())
     }
     pub fn body<I: IntoBody>(&mut self, body: I) {
-        self.body = body.into_body();
+        self.body = Body::Full(body.into_body());
+    }
+    /// Substitui o body, aceita bodies em stream
+    pub fn set_body(&mut self, body: Body) {
+        self.body = body;
+    }
+    /// Retira o body da resposta deixando um body vazio
+    pub fn take_body(&mut self) -> Body {
+        std::mem::take(&mut self.body)
+    }
+    /// Le um body em stream para a memoria, depois disso `get_body` retorna o conteudo
+    pub async fn read_body(&mut self) -> Result<()> {
+        let body = self.take_body().collect().await?;
+        self.body = Body::Full(body);
+        Ok(())
     }
     pub fn add_header(&mut self, key: &str, value: &str) -> Result<()> {
-        let mut h = match self.headers.clone() {
-            Some(headers) => headers,
-            None => HashMap::new(),
-        };
-        h.insert(key.to_string(), value.to_string());
-        self.headers = Some(h);
+        self.headers.insert(key, value);
+        Ok(())
+    }
+    /// Adiciona um valor sem substituir os existentes, usado por `Set-Cookie` e `Vary`
+    pub fn append_header(&mut self, key: &str, value: &str) -> Result<()> {
+        self.headers.append(key, value);
         Ok(())
     }
+    /// Indica se o body sera enviado com `Transfer-Encoding: chunked`
+    pub fn is_chunked(&self) -> bool {
+        self.headers
+            .get_all("Transfer-Encoding")
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case("chunked"))
+    }
     pub fn remove_header(&mut self, key: &str) -> Result<()> {
-        let mut h = match self.headers.clone() {
-            Some(headers) => headers,
-            None => HashMap::new(),
-        };
-        h.remove(key);
-        self.headers = Some(h);
+        self.headers.remove(key);
         Ok(())
     }
 }
 
-impl From<HttpResponse> for String {
-    fn from(value: HttpResponse) -> Self {
-        let res = value.clone();
+/// Codifica um pedaço do body no formato `<tamanho em hex>\r\n<dados>\r\n`
+pub(crate) fn encode_chunk(data: &[u8]) -> Vec<u8> {
+    let mut chunk = format!("{:X}\r\n", data.len()).into_bytes();
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(b"\r\n");
+    chunk
+}
+
+impl HttpResponse {
+    /// Define o que fazer com a conexão depois de enviar esta resposta
+    pub(crate) fn set_upgrade(&mut self, upgrade: OnUpgrade) {
+        self.upgrade = Some(upgrade);
+    }
+    pub(crate) fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.upgrade.take()
+    }
+
+    /// Indica se o cliente consegue saber onde o body termina sem fechar a conexão,
+    /// `chunked` diz se o cliente aceita `Transfer-Encoding: chunked`
+    pub(crate) fn is_delimited(&self, chunked: bool) -> bool {
+        !self.body.is_stream() || chunked || self.headers.contains_key("Content-Length")
+    }
+
+    /// Escreve a resposta, bodies em stream são enviados conforme os pedaços ficam prontos.
+    ///
+    /// Com `head_only`, resposta a um HEAD, apenas os headers são enviados, com o
+    /// mesmo Content-Length que um GET teria
+    pub(crate) async fn write_to<W>(
+        mut self,
+        w: &mut W,
+        chunked: bool,
+        head_only: bool,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        if self.is_chunked() && !chunked {
+            // HTTP/1.0 não entende chunked, o body volta a usar Content-Length
+            self.headers.remove("Transfer-Encoding");
+        }
+        // Respostas 1xx, 204 e 304 não tem body
+        let status = self.status_code;
+        if status.is_informational()
+            || status == StatusCode::NoContent
+            || status == StatusCode::NotModified
+        {
+            self.headers.remove("Content-Length");
+            self.headers.remove("Transfer-Encoding");
+            return write_head(w, &self.head()).await;
+        }
+        match self.take_body() {
+            Body::Full(bytes) => {
+                let out = if self.is_chunked() {
+                    // Sem Content-Length, o fim do body e marcado pelo chunk de tamanho 0
+                    self.headers.remove("Content-Length");
+                    let mut out = self.head();
+                    if head_only {
+                        return write_head(w, &out).await;
+                    }
+                    if !bytes.is_empty() {
+                        out.extend_from_slice(&encode_chunk(&bytes));
+                    }
+                    out.extend_from_slice(b"0\r\n\r\n");
+                    out
+                } else {
+                    self.headers
+                        .insert("Content-Length", &bytes.len().to_string());
+                    let mut out = self.head();
+                    if head_only {
+                        return write_head(w, &out).await;
+                    }
+                    out.extend_from_slice(&bytes);
+                    out
+                };
+                w.write_all(&out).await?;
+            }
+            Body::Stream(mut stream) => {
+                let use_chunks = !self.headers.contains_key("Content-Length") && chunked;
+                if use_chunks {
+                    self.headers.insert("Transfer-Encoding", "chunked");
+                }
+                if head_only {
+                    // O stream e descartado sem ser lido
+                    return write_head(w, &self.head()).await;
+                }
+                w.write_all(&self.head()).await?;
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk?;
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    if use_chunks {
+                        w.write_all(&encode_chunk(&chunk)).await?;
+                    } else {
+                        w.write_all(&chunk).await?;
+                    }
+                    w.flush().await?;
+                }
+                if use_chunks {
+                    w.write_all(b"0\r\n\r\n").await?;
+                }
+            }
+        }
+        w.flush().await
+    }
+
+    /// Linha de status e headers seguidos da linha vazia
+    fn head(&self) -> Vec<u8> {
         format!(
-            "{} {} {}\r\n{}Content-Length: {}\r\n\r\n{}",
-            &res.get_version(),
-            &res.get_status_code(),
-            &res.get_status_text(),
-            &res.get_headers(),
-            &res.get_body().len(),
-            &res.get_body()
+            "{} {} {}\r\n{}\r\n",
+            &self.get_version(),
+            &self.get_status_code(),
+            &self.get_status_text(),
+            &self.get_headers(),
         )
+        .into_bytes()
+    }
+}
+
+async fn write_head<W: AsyncWrite + Unpin>(w: &mut W, head: &[u8]) -> Result<()> {
+    w.write_all(head).await?;
+    w.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::HttpResponse;
+    use crate::{body::Body, status::StatusCode};
+
+    async fn written(res: HttpResponse, chunked: bool) -> String {
+        let mut out = Vec::new();
+        res.write_to(&mut out, chunked, false).await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn encodes_chunked_full_body() {
+        let mut res = HttpResponse::new(StatusCode::Ok, None, "hello");
+        res.add_header("Transfer-Encoding", "chunked").unwrap();
+        let out = written(res, true).await;
+        assert!(!out.contains("Content-Length"));
+        assert!(out.ends_with("\r\n\r\n5\r\nhello\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_content_length_without_chunked_support() {
+        let mut res = HttpResponse::new(StatusCode::Ok, None, "hello");
+        res.add_header("Transfer-Encoding", "chunked").unwrap();
+        let out = written(res, false).await;
+        assert!(!out.contains("Transfer-Encoding"));
+        assert!(out.contains("Content-Length:5\r\n"));
+        assert!(out.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn streams_body_in_chunks() {
+        let mut res = HttpResponse::new(StatusCode::Ok, None, "");
+        let parts = ["ab", "", "cde"].map(|part| Ok(Bytes::from(part)));
+        res.set_body(Body::from_stream(futures::stream::iter(parts)));
+        let out = written(res, true).await;
+        assert!(out.contains("Transfer-Encoding:chunked\r\n"));
+        assert!(out.ends_with("\r\n\r\n2\r\nab\r\n3\r\ncde\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn head_response_keeps_length_without_body() {
+        let res = HttpResponse::new(StatusCode::Ok, None, "hello");
+        let mut out = Vec::new();
+        res.write_to(&mut out, true, true).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Content-Length:5\r\n"));
+        assert!(out.ends_with("\r\n\r\n"));
+
+        let mut res = HttpResponse::new(StatusCode::Ok, None, "");
+        let parts = [Ok(Bytes::from("ab"))];
+        res.set_body(Body::from_stream(futures::stream::iter(parts)));
+        let mut out = Vec::new();
+        res.write_to(&mut out, true, true).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Transfer-Encoding:chunked\r\n"));
+        assert!(out.ends_with("\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn writes_each_set_cookie_on_its_own_line() {
+        let mut res = HttpResponse::new(StatusCode::Ok, None, "");
+        res.append_header("Set-Cookie", "a=1; Path=/").unwrap();
+        res.append_header("set-cookie", "b=2").unwrap();
+        res.append_header("Vary", "Accept").unwrap();
+        res.append_header("Vary", "Origin").unwrap();
+        let out = written(res, true).await;
+        assert!(out.contains("\r\nSet-Cookie:a=1; Path=/\r\nset-cookie:b=2\r\n"));
+        assert!(out.contains("\r\nVary:Accept\r\nVary:Origin\r\n"));
+    }
+
+    #[tokio::test]
+    async fn omits_body_for_no_content() {
+        let res = HttpResponse::new(StatusCode::NoContent, None, "ignored");
+        let out = written(res, true).await;
+        assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(out.ends_with("\r\n\r\n"));
+        assert!(!out.contains("ignored"));
     }
 }
@@ -1,61 +1,120 @@
 use std::{fmt::Debug, sync::Arc};
 
+use bytes::Bytes;
+
 use crate::{
     aplication::App,
+    context::Context,
+    fairing::{Fairing, IntoFairing},
     guard::{Guard, IntoGuard},
     handler::{Handler, IntoHandler},
-    request::Method,
+    request::{HttpRequest, Method},
+    tree::Pattern,
+    websocket::{self, IntoWsHandler},
 };
 
+/// Converte um valor nos bytes do body
 pub trait IntoBody: Debug {
-    fn into_body(self) -> String;
+    fn into_body(self) -> Bytes;
 }
 
 impl IntoBody for String {
-    fn into_body(self) -> String {
-        self
+    fn into_body(self) -> Bytes {
+        Bytes::from(self)
     }
 }
 
 impl IntoBody for &str {
-    fn into_body(self) -> String {
-        self.to_string()
+    fn into_body(self) -> Bytes {
+        Bytes::copy_from_slice(self.as_bytes())
+    }
+}
+
+impl IntoBody for Vec<u8> {
+    fn into_body(self) -> Bytes {
+        Bytes::from(self)
     }
 }
 
-pub struct RouteBuilder<'a> {
-    pub(crate) app: &'a mut App,
+impl IntoBody for &[u8] {
+    fn into_body(self) -> Bytes {
+        Bytes::copy_from_slice(self)
+    }
+}
+
+impl IntoBody for Bytes {
+    fn into_body(self) -> Bytes {
+        self
+    }
+}
+
+/// Destino das rotas criadas pelo `RouteBuilder`, como o `App` ou um `Scope`
+pub trait RouteTarget {
+    fn add_route(&mut self, route: Router);
+}
+
+impl RouteTarget for App {
+    fn add_route(&mut self, route: Router) {
+        App::add_route(self, route);
+    }
+}
+
+pub struct RouteBuilder<'a, T: RouteTarget = App> {
+    pub(crate) app: &'a mut T,
     pub(crate) pattern: String,
     pub(crate) method: Method,
     pub(crate) guards: Vec<Arc<dyn Guard>>,
+    pub(crate) name: Option<String>,
 }
 
-impl<'a> RouteBuilder<'a> {
+impl<'a, T: RouteTarget> RouteBuilder<'a, T> {
     pub fn faiting<M: IntoGuard>(mut self, guard: M) -> Self {
         self.guards.push(guard.into_guard());
         self
     }
-    pub fn handler<H>(self, handler: H) -> &'a mut App
+    /// Nome usado para gerar a URL da rota com `Context::url_for`, unico no App
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+    pub fn handler<H>(self, handler: H) -> &'a mut T
     where
         H: IntoHandler,
     {
-        let app_ref: &mut App = self.app;
+        let app_ref: &mut T = self.app;
         app_ref.add_route(Router {
-            pattern: self.pattern,
+            pattern: Pattern::parse(&self.pattern),
             handler: handler.into_handler(),
             method: self.method,
             guards: self.guards,
+            fairings: Vec::new(),
+            name: self.name,
         });
         app_ref
     }
+    /// Registra um handler WebSocket, a rota responde ao handshake e entrega
+    /// a conexão ao handler depois que os guards passarem
+    pub fn websocket<W>(self, handler: W) -> &'a mut T
+    where
+        W: IntoWsHandler,
+    {
+        let handler = handler.into_ws_handler();
+        self.handler(move |req: Arc<HttpRequest>, ctx: Arc<Context>| {
+            let handler = Arc::clone(&handler);
+            async move { websocket::upgrade(req, ctx, handler) }
+        })
+    }
 }
 
 #[derive(Clone)]
 pub struct Router {
-    pub(crate) pattern: String,
+    pub(crate) pattern: Pattern,
     pub(crate) handler: Handler,
     pub(crate) method: Method,
     pub(crate) guards: Vec<Arc<dyn Guard>>,
+    /// Fairings dos scopes da rota, executados depois dos globais
+    pub(crate) fairings: Vec<Arc<dyn Fairing>>,
+    pub(crate) name: Option<String>,
 }
 impl Router {
     pub fn new(
@@ -65,10 +124,237 @@ impl Router {
         middlewares: Vec<Arc<dyn Guard>>,
     ) -> Self {
         Router {
-            pattern: pattern.to_string(),
+            pattern: Pattern::parse(pattern),
             handler,
             method,
             guards: middlewares,
+            fairings: Vec::new(),
+            name: None,
+        }
+    }
+}
+
+/// Grupo de rotas com prefixo, guards e fairings em comum, criado por `App::scope`.
+///
+/// Guards e fairings do scope valem para todas as rotas dele, inclusive as
+/// registradas antes da chamada, e rodam antes dos definidos na propria rota
+/// ``` rust
+/// use milim_web::{request::Method::*, run_app, server, status::StatusCode, testing::TestClient};
+///
+/// let mut app = server();
+/// app.scope("/api/v1", |api| {
+///     api.route(Get, "/users").handler(|_req, _ctx| async move { "usuarios" });
+///     api.scope("/admin", |admin| {
+///         admin.route(Get, "/stats").handler(|_req, _ctx| async move { "stats" });
+///     });
+/// });
+///
+/// run_app(|| async {
+///     let client = TestClient::new(app).await;
+///     let res = client.get("/api/v1/admin/stats").send().await;
+///     assert_eq!(res.get_body_text(), "stats");
+///     let res = client.get("/users").send().await;
+///     assert_eq!(res.status(), &StatusCode::NotFound);
+/// });
+/// ```
+pub struct Scope {
+    prefix: String,
+    routes: Vec<Router>,
+    guards: Vec<Arc<dyn Guard>>,
+    fairings: Vec<Arc<dyn Fairing>>,
+}
+
+impl Scope {
+    pub(crate) fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            routes: Vec::new(),
+            guards: Vec::new(),
+            fairings: Vec::new(),
         }
     }
+    /// Adiciona uma rota, o pattern e relativo ao prefixo do scope
+    pub fn route(&mut self, method: Method, path: &str) -> RouteBuilder<'_, Scope> {
+        RouteBuilder {
+            app: self,
+            pattern: path.to_owned(),
+            method,
+            guards: Vec::new(),
+            name: None,
+        }
+    }
+    /// Cria um scope dentro deste, os prefixos são concatenados
+    pub fn scope<F>(&mut self, prefix: &str, f: F)
+    where
+        F: FnOnce(&mut Scope),
+    {
+        let mut scope = Scope::new(prefix);
+        f(&mut scope);
+        self.routes.extend(scope.into_routes());
+    }
+    /// Guard executado em todas as rotas do scope
+    pub fn guard<G: IntoGuard>(&mut self, guard: G) {
+        self.guards.push(guard.into_guard());
+    }
+    /// Fairing executado apenas para as rotas do scope
+    pub fn fairing<M: IntoFairing>(&mut self, fairing: M) {
+        self.fairings.push(fairing.into_fairing());
+    }
+
+    /// Aplica prefixo, guards e fairings do scope nas rotas
+    pub(crate) fn into_routes(self) -> Vec<Router> {
+        let Scope {
+            prefix,
+            mut routes,
+            guards,
+            fairings,
+        } = self;
+        for route in routes.iter_mut() {
+            route.pattern.prefix(&prefix);
+            route.guards.splice(0..0, guards.iter().cloned());
+            route.fairings.splice(0..0, fairings.iter().cloned());
+        }
+        routes
+    }
+}
+
+impl RouteTarget for Scope {
+    fn add_route(&mut self, route: Router) {
+        self.routes.push(route);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::{
+        context::Context,
+        fairing::Fairing,
+        guard::{Guard, Outcome},
+        request::{HttpRequest, Method::*},
+        response::HttpResponse,
+        server,
+        status::StatusCode,
+        testing::TestClient,
+    };
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Registra o nome no log e falha quando `deny` e verdadeiro
+    struct Record {
+        name: &'static str,
+        log: Log,
+        deny: bool,
+    }
+
+    #[async_trait]
+    impl Guard for Record {
+        async fn from_request(&self, _req: &HttpRequest, _ctx: &Context) -> Outcome {
+            self.log.lock().unwrap().push(self.name);
+            if self.deny {
+                Outcome::Failure(HttpResponse::new(StatusCode::Forbidden, None, self.name))
+            } else {
+                Outcome::Success
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Fairing for Record {
+        async fn on_request(&self, _req: &mut HttpRequest, _ctx: &Context) {
+            self.log.lock().unwrap().push(self.name);
+        }
+        async fn on_response(&self, _req: &HttpRequest, _res: &mut HttpResponse, _ctx: &Context) {}
+    }
+
+    fn record(name: &'static str, log: &Log) -> Record {
+        Record {
+            name,
+            log: Arc::clone(log),
+            deny: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn scope_guards_run_before_route_guards() {
+        let log = Log::default();
+        let mut app = server();
+        app.scope("/api", |api| {
+            // Registrada antes do guard do scope, que tambem vale para ela
+            api.route(Get, "/users")
+                .faiting(record("route", &log))
+                .handler(|_req, _ctx| async move { "users" });
+            api.guard(record("api", &log));
+            api.scope("/admin", |admin| {
+                admin.guard(record("admin", &log));
+                admin
+                    .route(Get, "/stats")
+                    .handler(|_req, _ctx| async move { "stats" });
+            });
+        });
+        app.route(Get, "/health")
+            .handler(|_req, _ctx| async move { "ok" });
+        let client = TestClient::new(app).await;
+
+        assert_eq!(
+            client.get("/api/users").send().await.get_body_text(),
+            "users"
+        );
+        assert_eq!(*log.lock().unwrap(), ["api", "route"]);
+
+        log.lock().unwrap().clear();
+        let res = client.get("/api/admin/stats").send().await;
+        assert_eq!(res.get_body_text(), "stats");
+        assert_eq!(*log.lock().unwrap(), ["api", "admin"]);
+
+        log.lock().unwrap().clear();
+        assert_eq!(client.get("/health").send().await.get_body_text(), "ok");
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_scope_guard_skips_route_guards_and_handler() {
+        let log = Log::default();
+        let mut app = server();
+        app.scope("/admin", |admin| {
+            admin.guard(Record {
+                deny: true,
+                ..record("admin", &log)
+            });
+            admin
+                .route(Get, "/")
+                .faiting(record("route", &log))
+                .handler(|_req, _ctx| async move { "admin" });
+        });
+        let client = TestClient::new(app).await;
+
+        let res = client.get("/admin").send().await;
+        assert_eq!(res.status(), &StatusCode::Forbidden);
+        assert_eq!(*log.lock().unwrap(), ["admin"]);
+    }
+
+    #[tokio::test]
+    async fn scope_fairings_run_after_global_ones_only_for_scope_routes() {
+        let log = Log::default();
+        let mut app = server();
+        app.fairing(record("global", &log));
+        app.scope("/api", |api| {
+            api.fairing(record("api", &log));
+            api.route(Get, "/users")
+                .handler(|_req, _ctx| async move { "users" });
+        });
+        app.route(Get, "/health")
+            .handler(|_req, _ctx| async move { "ok" });
+        let client = TestClient::new(app).await;
+
+        client.get("/api/users").send().await;
+        assert_eq!(*log.lock().unwrap(), ["global", "api"]);
+
+        log.lock().unwrap().clear();
+        client.get("/health").send().await;
+        assert_eq!(*log.lock().unwrap(), ["global"]);
+    }
 }
@@ -0,0 +1,450 @@
+use std::{io::Result, net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{net::TcpListener, sync::watch, task::JoinSet, time::timeout};
+
+use crate::{
+    aplication::App,
+    error::ServerError,
+    http2,
+    reader::RequestReader,
+    request::{ConnectionInfo, HttpRequestData, Method, Version},
+    response::HttpResponse,
+    shutdown::ShutdownHandle,
+    tls::TlsConfig,
+    upgrade::{Io, Upgraded},
+};
+
+/// Espera apos um erro ao aceitar conexão, evita repetir o erro em sequencia
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Servidor ja associado a um endereço, pronto para atender conexões.
+///
+/// Separar `bind` de `serve` permite descobrir a porta escolhida ao usar a porta 0
+/// ``` rust
+/// use milim_web::run_app;
+///
+/// run_app(|| async {
+///     let server = milim_web::server().bind("127.0.0.1:0").await.unwrap();
+///     let addr = server.local_addr().unwrap();
+///     assert_ne!(addr.port(), 0);
+///     println!("Escutando em {}", addr);
+///
+///     let shutdown = server.shutdown_handle();
+///     let serving = tokio::spawn(server.serve());
+///     shutdown.shutdown();
+///     serving.await.unwrap().unwrap();
+/// });
+/// ```
+pub struct Server {
+    app: App,
+    listener: TcpListener,
+}
+
+impl Server {
+    /// Cria o listener em `adress` para o App
+    pub async fn bind(app: App, adress: &str) -> Result<Self> {
+        let listener = TcpListener::bind(adress).await?;
+        Ok(Self::from_listener(app, listener))
+    }
+    /// Usa um listener ja criado
+    pub fn from_listener(app: App, listener: TcpListener) -> Self {
+        Self { app, listener }
+    }
+    /// Endereço em que o servidor esta escutando
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    /// Atende as conexões com HTTPS, o mesmo que `App::tls`
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.app.config.tls = Some(tls);
+        self
+    }
+    /// Handle que encerra o servidor
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.app.shutdown_handle()
+    }
+    /// Atende conexões ate o `ShutdownHandle` ser acionado
+    pub async fn serve(self) -> Result<()> {
+        self.serve_with_shutdown(std::future::pending()).await
+    }
+    /// Atende conexões ate `signal` completar ou o `ShutdownHandle` ser acionado
+    pub async fn serve_with_shutdown<F>(self, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let Server { mut app, listener } = self;
+        // Certificado e chave invalidos impedem o servidor de iniciar
+        let acceptor = app
+            .config
+            .tls
+            .as_ref()
+            .map(TlsConfig::acceptor)
+            .transpose()?;
+        println!(" > Max body size: {}KB", app.config.max_body_kb);
+        println!(" > Keep alive: {}s", app.config.keep_alive_s);
+        println!(" > Max headers: {}", app.config.max_headers);
+        println!(" > TLS: {}", acceptor.is_some());
+
+        // on_ready roda antes do contexto ser compartilhado entre as tasks
+        let fairings = app.lifecycle_fairings();
+        let context = Arc::get_mut(&mut app.context).ok_or_else(|| {
+            std::io::Error::other("Nao e possivel iniciar o servidor com o contexto compartilhado")
+        })?;
+        for fairing in fairings.iter() {
+            fairing.on_ready(context).await;
+        }
+
+        // Rotas, fairings e estado sao compartilhados de forma imutavel entre as conexões
+        let app = Arc::new(app);
+        let (draining, _) = watch::channel(false);
+        let mut connections = JoinSet::new();
+        tokio::pin!(signal);
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    // Erros como EMFILE são temporarios, o servidor continua aceitando
+                    let (socket, peer_addr) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            println!("Erro ao aceitar conexão: {}", e);
+                            tokio::time::sleep(ACCEPT_BACKOFF).await;
+                            continue;
+                        }
+                    };
+                    let app = Arc::clone(&app);
+                    let acceptor = acceptor.clone();
+                    let draining = draining.subscribe();
+                    connections.spawn(async move {
+                        let mut conn = ConnectionInfo {
+                            peer_addr: Some(peer_addr),
+                            ..Default::default()
+                        };
+                        let result = match acceptor {
+                            Some(acceptor) => {
+                                // O handshake tambem deve terminar dentro de read_timeout_s
+                                let handshake = Duration::from_secs(app.config.read_timeout_s.into());
+                                match timeout(handshake, acceptor.accept(socket)).await {
+                                    Ok(Ok(stream)) => {
+                                        conn.secure = true;
+                                        conn.alpn = stream.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
+                                        if conn.alpn.as_deref() == Some(b"h2") {
+                                            http2::serve_connection(&app, stream, conn, draining).await
+                                        } else {
+                                            handle_connection(&app, stream, conn, draining).await
+                                        }
+                                    }
+                                    Ok(Err(e)) => Err(e),
+                                    Err(_) => Ok(()),
+                                }
+                            }
+                            None => handle_connection(&app, socket, conn, draining).await,
+                        };
+                        if let Err(e) = result {
+                            println!("Erro na conexão: {}", e);
+                        }
+                    });
+                    // Descarta as conexões ja finalizadas
+                    while connections.try_join_next().is_some() {}
+                }
+                _ = &mut signal => break,
+                _ = app.shutdown.wait() => break,
+            }
+        }
+
+        // Para de aceitar conexões e espera as requisições em andamento
+        drop(listener);
+        draining.send_replace(true);
+        let grace = Duration::from_secs(app.config.shutdown_grace_s.into());
+        let drained = timeout(grace, async {
+            while connections.join_next().await.is_some() {}
+        })
+        .await;
+        if drained.is_err() {
+            connections.abort_all();
+        }
+
+        for fairing in fairings.iter() {
+            fairing.on_shutdown(&app.context).await;
+        }
+        Ok(())
+    }
+}
+
+/// Atende as requisições de uma conexão ate o cliente pedir `Connection: close`,
+/// a conexão ficar ociosa por mais de `keep_alive_s` ou o servidor ser encerrado
+async fn handle_connection<S: Io>(
+    app: &Arc<App>,
+    mut socket: S,
+    conn: ConnectionInfo,
+    mut draining: watch::Receiver<bool>,
+) -> Result<()> {
+    let keep_alive = Duration::from_secs(app.config.keep_alive_s.into());
+    let mut reader = RequestReader::new(&app.config);
+    // A primeira requisição deve começar dentro de read_timeout_s
+    let mut idle = Duration::from_secs(app.config.read_timeout_s.into());
+    let mut first = true;
+
+    loop {
+        let ready = tokio::select! {
+            ready = reader.wait_request(&mut socket, idle) => ready,
+            // Conexões ociosas são fechadas no encerramento
+            _ = draining.wait_for(|draining| *draining) => Ok(false),
+        };
+        let raw = match ready {
+            Ok(true) => reader.next_request(&mut socket).await,
+            Ok(false) => return Ok(()),
+            Err(e) => Err(e),
+        };
+        // HTTP/2 com conhecimento previo, o preface so e aceito no inicio da conexão
+        if first && matches!(&raw, Ok(raw) if raw.bytes == http2::PREFACE_HEAD) {
+            let mut prefix = http2::PREFACE_HEAD.to_vec();
+            prefix.extend_from_slice(&reader.into_buffer());
+            let io = Upgraded::new(socket, prefix.into());
+            return http2::serve_connection(app, io, conn, draining).await;
+        }
+        let parsed = raw.and_then(|raw| {
+            let mut req_data = HttpRequestData::parse(&raw.bytes)?;
+            req_data.trailers = raw.trailers;
+            Ok(req_data)
+        });
+        let req_data = match parsed {
+            Ok(req_data) => req_data,
+            Err(ServerError::Io(e)) => return Err(e),
+            Err(e) => {
+                // Requisição invalida, responde com o status do erro e fecha a conexão
+                if let Some(status) = e.status_code() {
+                    let mut res = HttpResponse::new(status, None, "");
+                    res.add_header("Connection", "close")?;
+                    res.write_to(&mut socket, false, false).await?;
+                }
+                return Ok(());
+            }
+        };
+        idle = keep_alive;
+        first = false;
+
+        let keep_alive_requested = req_data.keep_alive();
+        let supports_chunked = req_data.version != Version::V1_0;
+        let head_only = req_data.method == Method::Head;
+
+        let mut res = app.dispatch(req_data, conn.clone()).await;
+        if let Some(on_upgrade) = res.take_upgrade() {
+            // A conexão passa a ser do protocolo negociado, como WebSocket
+            res.write_to(&mut socket, supports_chunked, false).await?;
+            (on_upgrade.0)(Upgraded::new(socket, reader.into_buffer())).await;
+            return Ok(());
+        }
+        // Durante o encerramento a conexão e fechada apos a resposta, assim como
+        // quando o fim de um body em stream so pode ser indicado fechando a conexão
+        let persistent = keep_alive_requested
+            && !*draining.borrow()
+            && (head_only || res.is_delimited(supports_chunked));
+        if persistent {
+            res.add_header("Connection", "keep-alive")?;
+            res.add_header(
+                "Keep-Alive",
+                &format!("timeout={}", app.config.keep_alive_s),
+            )?;
+        } else {
+            res.add_header("Connection", "close")?;
+        }
+        res.write_to(&mut socket, supports_chunked, head_only)
+            .await?;
+
+        if !persistent {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        time::timeout,
+    };
+
+    use crate::{
+        aplication::App,
+        config::Config,
+        context::Context,
+        fairing::Fairing,
+        request::{HttpRequest, Method::*},
+        response::HttpResponse,
+        server,
+    };
+
+    /// Inicia o servidor em uma porta livre
+    async fn start(app: App) -> SocketAddr {
+        let server = app.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+        addr
+    }
+
+    /// Envia `raw` e le tudo ate o servidor fechar a conexão
+    async fn exchange(addr: SocketAddr, raw: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(raw).await.unwrap();
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        String::from_utf8_lossy(&out).to_string()
+    }
+
+    #[tokio::test]
+    async fn answers_malformed_requests_and_closes() {
+        let mut app = server();
+        app.route(Get, "/")
+            .handler(|_req, _ctx| async move { "ok" });
+        let addr = start(app).await;
+
+        let out = exchange(addr, b"GET / HTTP/1.1\r\nBad Header: x\r\n\r\n").await;
+        assert!(out.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(out.contains("Connection:close\r\n"));
+
+        let out = exchange(addr, b"GET / HTTP/3.0\r\n\r\n").await;
+        assert!(out.starts_with("HTTP/1.1 505 HTTP Version Not Supported\r\n"));
+    }
+
+    #[tokio::test]
+    async fn slow_handler_does_not_block_other_connections() {
+        let mut app = server();
+        app.route(Get, "/slow").handler(|_req, _ctx| async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            "slow"
+        });
+        app.route(Get, "/fast")
+            .handler(|_req, _ctx| async move { "fast" });
+        let addr = start(app).await;
+
+        let slow = tokio::spawn(exchange(
+            addr,
+            b"GET /slow HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let fast = timeout(
+            Duration::from_millis(500),
+            exchange(addr, b"GET /fast HTTP/1.1\r\nConnection: close\r\n\r\n"),
+        )
+        .await
+        .expect("a rota rapida esperou a rota lenta");
+        assert!(fast.ends_with("fast"));
+        assert!(!slow.is_finished());
+        assert!(slow.await.unwrap().ends_with("slow"));
+    }
+
+    #[tokio::test]
+    async fn head_responses_keep_pipelined_connection_in_sync() {
+        let mut app = server();
+        app.route(Head, "/")
+            .handler(|_req, _ctx| async move { "BODYBODY" });
+        app.route(Get, "/")
+            .handler(|_req, _ctx| async move { "ok" });
+        let addr = start(app).await;
+
+        let out = exchange(
+            addr,
+            b"HEAD / HTTP/1.1\r\n\r\nHEAD /missing HTTP/1.1\r\n\r\n\
+              GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        let responses: Vec<_> = out.split("\r\n\r\n").collect();
+        assert_eq!(responses.len(), 4, "{:?}", out);
+        assert!(responses[0].starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(responses[0].contains("Content-Length:8"));
+        assert!(responses[1].starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(responses[1].contains("Content-Length:9"));
+        assert!(responses[2].starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(responses[3], "ok");
+    }
+
+    #[tokio::test]
+    async fn chunked_trailers_reach_handler_separately() {
+        let mut app = server();
+        app.route(Post, "/")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let req = &req.raw;
+                format!(
+                    "{}|{}|{}|{}",
+                    req.headers.get("host").unwrap_or(""),
+                    req.headers.get("content-length").unwrap_or(""),
+                    req.get_body_text(),
+                    req.trailers().get("host").unwrap_or(""),
+                )
+            });
+        let addr = start(app).await;
+
+        let out = exchange(
+            addr,
+            b"POST / HTTP/1.1\r\nHost: app\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+              2\r\nhi\r\n0\r\nHost: evil\r\n\r\n",
+        )
+        .await;
+        assert!(out.ends_with("\r\n\r\napp|2|hi|evil"), "{:?}", out);
+    }
+
+    #[tokio::test]
+    async fn closes_connection_without_first_request() {
+        let mut app = server();
+        app.config(Config {
+            read_timeout_s: 1,
+            keep_alive_s: 60,
+            ..Config::new()
+        });
+        let addr = start(app).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut out = Vec::new();
+        let read = timeout(Duration::from_secs(3), stream.read_to_end(&mut out)).await;
+        assert!(matches!(read, Ok(Ok(0))));
+    }
+
+    struct Flag(Arc<AtomicBool>);
+
+    #[async_trait]
+    impl Fairing for Flag {
+        async fn on_request(&self, _req: &mut HttpRequest, _ctx: &Context) {}
+        async fn on_response(&self, _req: &HttpRequest, _res: &mut HttpResponse, _ctx: &Context) {}
+        async fn on_shutdown(&self, _ctx: &Context) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_requests_in_flight() {
+        let shut_down = Arc::new(AtomicBool::new(false));
+        let mut app = server();
+        app.fairing(Flag(Arc::clone(&shut_down)));
+        app.route(Get, "/slow").handler(|_req, _ctx| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            "done"
+        });
+        let server = app.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle();
+        let serving = tokio::spawn(server.serve());
+
+        let request = tokio::spawn(exchange(addr, b"GET /slow HTTP/1.1\r\n\r\n"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.shutdown();
+
+        let out = request.await.unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(out.contains("Connection:close\r\n"));
+        assert!(out.ends_with("done"));
+        serving.await.unwrap().unwrap();
+        assert!(shut_down.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Permite encerrar o servidor de outra task.
+///
+/// Ao ser acionado o servidor para de aceitar conexões, espera as requisições
+/// em andamento terminarem dentro de `shutdown_grace_s` e executa `Fairing::on_shutdown`
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self {
+            tx: Arc::new(watch::channel(false).0),
+        }
+    }
+    /// Solicita o encerramento do servidor
+    pub fn shutdown(&self) {
+        self.tx.send_replace(true);
+    }
+    /// Indica se o encerramento ja foi solicitado
+    pub fn is_shutdown(&self) -> bool {
+        *self.tx.borrow()
+    }
+    /// Espera ate o encerramento ser solicitado
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        let _ = rx.wait_for(|shutdown| *shutdown).await;
+    }
+}
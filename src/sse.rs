@@ -0,0 +1,267 @@
+/*!
+Responder para Server-Sent Events.
+
+``` rust
+use std::time::Duration;
+
+use futures::StreamExt;
+use milim_web::{request::Method::*, run_app, server, sse::{Event, Sse}, testing::TestClient};
+
+let mut app = server();
+app.route(Get, "/progresso").handler(|_req, _ctx| async move {
+    let events = futures::stream::iter((0..=100).step_by(10))
+        .map(|p| Event::new(p.to_string()).event("progresso"));
+    Sse::new(events).keep_alive(Duration::from_secs(10))
+});
+
+run_app(|| async {
+    let client = TestClient::new(app).await;
+    let mut res = client.get("/progresso").send().await;
+    res.read_body().await.unwrap();
+    assert!(res.get_body_text().starts_with("event: progresso\ndata: 0\n\n"));
+});
+```
+*/
+use std::{pin::Pin, time::Duration};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use tokio::time::timeout;
+
+use crate::{
+    body::Body, header::HeaderMap, responder::Responder, response::HttpResponse, status::StatusCode,
+};
+
+/// Um evento enviado ao cliente
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<Duration>,
+}
+
+impl Event {
+    pub fn new<D: Into<String>>(data: D) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+    pub fn id<I: Into<String>>(mut self, id: I) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+    /// Nome do evento, o cliente escuta com `addEventListener(nome, ...)`
+    pub fn event<E: Into<String>>(mut self, event: E) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+    /// Tempo que o cliente espera antes de reconectar
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Formata o evento, cada linha de `data` vira um campo `data:`.
+    ///
+    /// `\r\n`, `\r` e `\n` terminam uma linha para o cliente, então todos separam
+    /// linhas de `data` e são removidos de id e event
+    fn to_bytes(&self) -> Bytes {
+        // Quebras de linha em id e event terminariam o campo
+        let single_line = |v: &str| v.replace(['\r', '\n'], "");
+        let mut out = String::new();
+        if let Some(id) = &self.id {
+            out.push_str(&format!("id: {}\n", single_line(id)));
+        }
+        if let Some(event) = &self.event {
+            out.push_str(&format!("event: {}\n", single_line(event)));
+        }
+        if let Some(retry) = self.retry {
+            out.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        for line in self
+            .data
+            .split("\r\n")
+            .flat_map(|line| line.split(['\r', '\n']))
+        {
+            out.push_str(&format!("data: {}\n", line));
+        }
+        out.push('\n');
+        Bytes::from(out)
+    }
+}
+
+/// Resposta `text/event-stream` gerada a partir de um stream de eventos.
+///
+/// Sem eventos por `keep_alive` um comentario e enviado, assim a conexão não e
+/// derrubada por proxies e um cliente desconectado e detectado na escrita,
+/// o que descarta o stream de eventos
+pub struct Sse {
+    events: Pin<Box<dyn Stream<Item = Event> + Send + 'static>>,
+    keep_alive: Option<Duration>,
+}
+
+impl Sse {
+    pub fn new<S>(events: S) -> Self
+    where
+        S: Stream<Item = Event> + Send + 'static,
+    {
+        Self {
+            events: Box::pin(events),
+            keep_alive: Some(Duration::from_secs(15)),
+        }
+    }
+    /// Intervalo maximo sem envio antes de um comentario de keep-alive, padrão de 15s
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+    /// Desativa os comentarios de keep-alive
+    pub fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+
+    fn into_body(self) -> Body {
+        let Sse { events, keep_alive } = self;
+        Body::from_stream(stream::unfold(events, move |mut events| async move {
+            let chunk = match keep_alive {
+                Some(interval) => match timeout(interval, events.next()).await {
+                    Ok(event) => event?.to_bytes(),
+                    Err(_) => Bytes::from_static(b":\n\n"),
+                },
+                None => events.next().await?.to_bytes(),
+            };
+            Some((Ok(chunk), events))
+        }))
+    }
+}
+
+impl Responder for Sse {
+    fn into_response(self: Box<Self>) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "text/event-stream");
+        headers.insert("Cache-Control", "no-cache");
+        let mut res = HttpResponse::new(StatusCode::Ok, Some(headers), "");
+        res.set_body(self.into_body());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        time::timeout,
+    };
+
+    use super::{Event, Sse};
+    use crate::{request::Method::*, server, testing::TestClient};
+
+    #[test]
+    fn formats_event_fields() {
+        let event = Event::new("a\r\nb")
+            .id("1\n2")
+            .event("tick")
+            .retry(Duration::from_millis(1500));
+        assert_eq!(
+            event.to_bytes(),
+            "id: 12\nevent: tick\nretry: 1500\ndata: a\ndata: b\n\n"
+        );
+    }
+
+    #[test]
+    fn carriage_return_cannot_inject_fields() {
+        let event = Event::new("a\revent: admin\rid: 9\n\nb")
+            .id("1\r2")
+            .event("x\ry");
+        assert_eq!(
+            event.to_bytes(),
+            "id: 12\nevent: xy\ndata: a\ndata: event: admin\ndata: id: 9\ndata: \ndata: b\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_events_and_keep_alive_comments() {
+        let mut app = server();
+        app.route(Get, "/events").handler(|_req, _ctx| async move {
+            let events = futures::stream::unfold(0, |i| async move {
+                if i == 2 {
+                    return None;
+                }
+                tokio::time::sleep(Duration::from_millis(if i == 1 { 80 } else { 0 })).await;
+                Some((Event::new(i.to_string()), i + 1))
+            });
+            Sse::new(events).keep_alive(Duration::from_millis(30))
+        });
+        let client = TestClient::new(app).await;
+
+        let mut res = client.get("/events").send().await;
+        assert_eq!(res.header("Content-Type"), Some("text/event-stream"));
+        res.read_body().await.unwrap();
+        let body = res.get_body_text();
+        assert!(body.starts_with("data: 0\n\n:\n\n"), "{:?}", body);
+        assert!(body.ends_with(":\n\ndata: 1\n\n"), "{:?}", body);
+    }
+
+    /// Marca quando o stream de eventos e descartado
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn disconnected_client_drops_event_stream() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&dropped);
+        let mut app = server();
+        app.route(Get, "/events").handler(move |_req, _ctx| {
+            let flag = DropFlag(Arc::clone(&flag));
+            async move {
+                let events = futures::stream::unfold(flag, |flag| async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Some((Event::new("tick"), flag))
+                });
+                Sse::new(events).without_keep_alive()
+            }
+        });
+        let server = app.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET /events HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = [0; 1024];
+        let mut received = Vec::new();
+        while !received.ends_with(b"data: tick\n\n\r\n") {
+            let n = client.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(client);
+
+        timeout(Duration::from_secs(2), async {
+            while !dropped.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("o stream continuou apos o cliente desconectar");
+    }
+}
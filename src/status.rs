@@ -1,9 +1,207 @@
-#[derive(PartialEq, Clone, Debug)]
-pub enum StatusCode {
-    Ok,
-    BadRequest,
-    NotFound,
-    MethodNotAllowed,
-    InternalServerError,
-    NotAutorized,
+use std::fmt;
+
+use crate::error::InvalidStatusCode;
+
+macro_rules! status_codes {
+    ($($code:literal => $variant:ident, $reason:literal;)*) => {
+        /// Status de uma resposta http, com todos os codigos registrados na IANA.
+        ///
+        /// Codigos não registrados são criados com `StatusCode::try_from`, que
+        /// devolve a variante propria para codigos registrados
+        /// ``` rust
+        /// use milim_web::status::StatusCode;
+        ///
+        /// assert_eq!(StatusCode::NotFound.as_u16(), 404);
+        /// assert_eq!(StatusCode::NotFound.to_string(), "404 Not Found");
+        /// assert_eq!(StatusCode::try_from(404), Ok(StatusCode::NotFound));
+        /// assert_eq!(StatusCode::try_from(418).unwrap().as_u16(), 418);
+        /// assert!(StatusCode::try_from(42).is_err());
+        /// assert!(StatusCode::try_from(1000).is_err());
+        /// ```
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+        pub enum StatusCode {
+            $($variant,)*
+            Custom(CustomCode),
+        }
+
+        impl StatusCode {
+            /// Codigo numerico do status
+            pub fn as_u16(&self) -> u16 {
+                match self {
+                    $(StatusCode::$variant => $code,)*
+                    StatusCode::Custom(code) => code.0,
+                }
+            }
+            /// Frase padrão do status, `None` para codigos não registrados
+            pub fn canonical_reason(&self) -> Option<&'static str> {
+                match self.as_u16() {
+                    $($code => Some($reason),)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// Aceita codigos de 100 a 999, os não registrados viram `StatusCode::Custom`
+        impl TryFrom<u16> for StatusCode {
+            type Error = InvalidStatusCode;
+
+            fn try_from(value: u16) -> Result<Self, Self::Error> {
+                match value {
+                    $($code => Ok(StatusCode::$variant),)*
+                    100..=999 => Ok(StatusCode::Custom(CustomCode(value))),
+                    _ => Err(InvalidStatusCode(value)),
+                }
+            }
+        }
+    };
+}
+
+/// Codigo de status sem variante propria em `StatusCode`.
+///
+/// So pode ser criado por `StatusCode::try_from`, assim um mesmo codigo tem
+/// uma unica representação
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct CustomCode(u16);
+
+impl CustomCode {
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+}
+
+status_codes! {
+    100 => Continue, "Continue";
+    101 => SwitchingProtocols, "Switching Protocols";
+    102 => Processing, "Processing";
+    103 => EarlyHints, "Early Hints";
+    200 => Ok, "OK";
+    201 => Created, "Created";
+    202 => Accepted, "Accepted";
+    203 => NonAuthoritativeInformation, "Non-Authoritative Information";
+    204 => NoContent, "No Content";
+    205 => ResetContent, "Reset Content";
+    206 => PartialContent, "Partial Content";
+    207 => MultiStatus, "Multi-Status";
+    208 => AlreadyReported, "Already Reported";
+    226 => ImUsed, "IM Used";
+    300 => MultipleChoices, "Multiple Choices";
+    301 => MovedPermanently, "Moved Permanently";
+    302 => Found, "Found";
+    303 => SeeOther, "See Other";
+    304 => NotModified, "Not Modified";
+    305 => UseProxy, "Use Proxy";
+    307 => TemporaryRedirect, "Temporary Redirect";
+    308 => PermanentRedirect, "Permanent Redirect";
+    400 => BadRequest, "Bad Request";
+    401 => Unauthorized, "Unauthorized";
+    402 => PaymentRequired, "Payment Required";
+    403 => Forbidden, "Forbidden";
+    404 => NotFound, "Not Found";
+    405 => MethodNotAllowed, "Method Not Allowed";
+    406 => NotAcceptable, "Not Acceptable";
+    407 => ProxyAuthenticationRequired, "Proxy Authentication Required";
+    408 => RequestTimeout, "Request Timeout";
+    409 => Conflict, "Conflict";
+    410 => Gone, "Gone";
+    411 => LengthRequired, "Length Required";
+    412 => PreconditionFailed, "Precondition Failed";
+    413 => PayloadTooLarge, "Payload Too Large";
+    414 => UriTooLong, "URI Too Long";
+    415 => UnsupportedMediaType, "Unsupported Media Type";
+    416 => RangeNotSatisfiable, "Range Not Satisfiable";
+    417 => ExpectationFailed, "Expectation Failed";
+    421 => MisdirectedRequest, "Misdirected Request";
+    422 => UnprocessableEntity, "Unprocessable Entity";
+    423 => Locked, "Locked";
+    424 => FailedDependency, "Failed Dependency";
+    425 => TooEarly, "Too Early";
+    426 => UpgradeRequired, "Upgrade Required";
+    428 => PreconditionRequired, "Precondition Required";
+    429 => TooManyRequests, "Too Many Requests";
+    431 => RequestHeaderFieldsTooLarge, "Request Header Fields Too Large";
+    451 => UnavailableForLegalReasons, "Unavailable For Legal Reasons";
+    500 => InternalServerError, "Internal Server Error";
+    501 => NotImplemented, "Not Implemented";
+    502 => BadGateway, "Bad Gateway";
+    503 => ServiceUnavailable, "Service Unavailable";
+    504 => GatewayTimeout, "Gateway Timeout";
+    505 => HttpVersionNotSupported, "HTTP Version Not Supported";
+    506 => VariantAlsoNegotiates, "Variant Also Negotiates";
+    507 => InsufficientStorage, "Insufficient Storage";
+    508 => LoopDetected, "Loop Detected";
+    510 => NotExtended, "Not Extended";
+    511 => NetworkAuthenticationRequired, "Network Authentication Required";
+}
+
+impl StatusCode {
+    #[deprecated(note = "use StatusCode::Unauthorized")]
+    #[allow(non_upper_case_globals)]
+    pub const NotAutorized: StatusCode = StatusCode::Unauthorized;
+
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.as_u16())
+    }
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.as_u16())
+    }
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.as_u16())
+    }
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.as_u16())
+    }
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.as_u16())
+    }
+}
+
+/// Formata como na linha de status, exemplo `404 Not Found`
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.as_u16(),
+            self.canonical_reason().unwrap_or("<unknown status code>")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusCode;
+    use crate::error::InvalidStatusCode;
+
+    #[test]
+    fn registered_codes_always_use_their_variant() {
+        for code in [100, 204, 304, 404, 511] {
+            let status = StatusCode::try_from(code).unwrap();
+            assert!(!matches!(status, StatusCode::Custom(_)), "{}", code);
+            assert_eq!(status.as_u16(), code);
+        }
+        assert_eq!(StatusCode::try_from(404), Ok(StatusCode::NotFound));
+    }
+
+    #[test]
+    fn unregistered_codes_are_custom() {
+        let status = StatusCode::try_from(499).unwrap();
+        assert!(matches!(status, StatusCode::Custom(code) if code.as_u16() == 499));
+        assert!(status.is_client_error());
+        assert_eq!(status.canonical_reason(), None);
+        assert_eq!(status, StatusCode::try_from(499).unwrap());
+    }
+
+    #[test]
+    fn rejects_codes_outside_three_digits() {
+        for code in [0, 42, 99, 1000] {
+            assert_eq!(StatusCode::try_from(code), Err(InvalidStatusCode(code)));
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn keeps_deprecated_not_autorized_alias() {
+        assert_eq!(StatusCode::NotAutorized, StatusCode::Unauthorized);
+        assert!(matches!(StatusCode::Unauthorized, StatusCode::NotAutorized));
+    }
 }
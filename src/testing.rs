@@ -0,0 +1,113 @@
+/*!
+Ferramentas para testar handlers sem abrir sockets.
+
+O `TestClient` envia requisições direto pelo roteamento, fairings, guards e handlers
+``` rust
+use milim_web::{request::Method::*, run_app, server, status::StatusCode, testing::TestClient};
+
+let mut app = server();
+app.route(Get, "/hello")
+    .handler(|_req, _ctx| async move { "Hello World!!" });
+
+run_app(|| async {
+    let client = TestClient::new(app).await;
+    let res = client.get("/hello").send().await;
+    assert_eq!(res.status(), &StatusCode::Ok);
+    assert_eq!(res.get_body_text(), "Hello World!!");
+});
+```
+*/
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::{
+    aplication::App,
+    header::HeaderMap,
+    query::QueryMap,
+    request::{ConnectionInfo, HttpRequestData, Method, Resource, Version},
+    response::HttpResponse,
+    router::IntoBody,
+};
+
+/// Executa o pipeline do App em memoria
+pub struct TestClient {
+    app: App,
+}
+
+impl TestClient {
+    /// Cria o cliente executando os `on_ready` dos fairings como o servidor faria
+    pub async fn new(mut app: App) -> Self {
+        let fairings = app.lifecycle_fairings();
+        let context = Arc::get_mut(&mut app.context)
+            .expect("Nao e possivel testar o App com o contexto compartilhado");
+        for fairing in fairings.iter() {
+            fairing.on_ready(context).await;
+        }
+        Self { app }
+    }
+    /// Envia uma requisição ja montada, bodies em stream devem ser lidos
+    /// com `HttpResponse::read_body`
+    pub async fn send(&self, req: HttpRequestData) -> HttpResponse {
+        self.app.dispatch(req, ConnectionInfo::default()).await
+    }
+    /// Envia a requisição crua, head e body, respondendo como o servidor
+    /// quando o parse falha
+    pub async fn send_raw(&self, raw: &[u8]) -> HttpResponse {
+        match HttpRequestData::parse(raw) {
+            Ok(req) => self.send(req).await,
+            Err(e) => HttpResponse::new(e.status_code(), None, ""),
+        }
+    }
+    /// Começa uma requisição com o metodo e o caminho (com query) informados
+    pub fn request(&self, method: Method, path: &str) -> TestRequest<'_> {
+        TestRequest {
+            client: self,
+            data: HttpRequestData {
+                method,
+                version: Version::V1_1,
+                resource: Resource::Path(path.to_string()),
+                headers: HeaderMap::new(),
+                body: Bytes::new(),
+                trailers: HeaderMap::new(),
+                params: None,
+                queryes: QueryMap::new(),
+            },
+        }
+    }
+    pub fn get(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::Get, path)
+    }
+    pub fn post(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::Post, path)
+    }
+    pub fn put(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::Put, path)
+    }
+    pub fn patch(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::Patch, path)
+    }
+    pub fn delete(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::Delete, path)
+    }
+}
+
+/// Requisição em construção criada pelo `TestClient`
+pub struct TestRequest<'a> {
+    client: &'a TestClient,
+    data: HttpRequestData,
+}
+
+impl TestRequest<'_> {
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.data.headers.append(key, value);
+        self
+    }
+    pub fn body<I: IntoBody>(mut self, body: I) -> Self {
+        self.data.body = body.into_body();
+        self
+    }
+    pub async fn send(self) -> HttpResponse {
+        self.client.send(self.data).await
+    }
+}
@@ -0,0 +1,226 @@
+/*!
+Terminação TLS com rustls.
+
+``` rust,no_run
+use milim_web::{server, tls::TlsConfig};
+
+# async fn run() -> std::io::Result<()> {
+let mut app = server();
+app.tls(TlsConfig::new("certs/cert.pem", "certs/key.pem"));
+app.listen("0.0.0.0:8443").await
+# }
+```
+*/
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+
+/// Certificado e chave usados para atender HTTPS
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// Cadeia de certificados em PEM, começando pelo certificado do servidor
+    pub cert_path: PathBuf,
+    /// Chave privada em PEM, PKCS#8, PKCS#1 ou SEC1
+    pub key_path: PathBuf,
+    /// Protocolos oferecidos via ALPN, em ordem de preferencia, `h2` e `http/1.1` por padrão
+    pub alpn: Vec<Vec<u8>>,
+}
+
+impl TlsConfig {
+    pub fn new<C: Into<PathBuf>, K: Into<PathBuf>>(cert_path: C, key_path: K) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            alpn: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        }
+    }
+    /// Substitui os protocolos oferecidos via ALPN
+    pub fn alpn<P: AsRef<[u8]>>(mut self, protocols: &[P]) -> Self {
+        self.alpn = protocols.iter().map(|p| p.as_ref().to_vec()).collect();
+        self
+    }
+
+    /// Le os arquivos PEM e cria o acceptor
+    pub(crate) fn acceptor(&self) -> Result<TlsAcceptor> {
+        let invalid = |e: &dyn std::fmt::Display, path: &PathBuf| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+            .map_err(|e| invalid(&e, &self.cert_path))?;
+        if certs.is_empty() {
+            return Err(invalid(&"nenhum certificado encontrado", &self.cert_path));
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .map_err(|e| invalid(&e, &self.key_path))?;
+
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| invalid(&e, &self.cert_path))?;
+        config.alpn_protocols = self.alpn.clone();
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, net::SocketAddr, path::PathBuf, sync::Arc};
+
+    use bytes::Bytes;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_rustls::{
+        TlsConnector,
+        client::TlsStream,
+        rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+    };
+
+    use super::TlsConfig;
+    use crate::{
+        request::{HttpRequest, Method::*},
+        server,
+    };
+
+    #[test]
+    fn alpn_replaces_default_protocols() {
+        let config = TlsConfig::new("cert.pem", "key.pem");
+        assert_eq!(config.alpn, [b"h2".to_vec(), b"http/1.1".to_vec()]);
+        let config = config.alpn(&["http/1.1"]);
+        assert_eq!(config.alpn, [b"http/1.1".to_vec()]);
+    }
+
+    #[test]
+    fn missing_files_are_reported_with_their_path() {
+        let err = TlsConfig::new("/nao/existe/cert.pem", "/nao/existe/key.pem")
+            .acceptor()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().starts_with("/nao/existe/cert.pem:"));
+    }
+
+    #[test]
+    fn rejects_pem_without_certificates() {
+        let cert = std::env::temp_dir().join(format!("milim-tls-{}.pem", std::process::id()));
+        std::fs::write(&cert, "").unwrap();
+        let err = TlsConfig::new(&cert, "/nao/existe/key.pem")
+            .acceptor()
+            .err()
+            .unwrap();
+        std::fs::remove_file(&cert).unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().ends_with("nenhum certificado encontrado"));
+    }
+
+    /// Certificado autoassinado para `localhost`, gravado em arquivos temporarios
+    struct SelfSigned {
+        cert: PathBuf,
+        key: PathBuf,
+        roots: RootCertStore,
+    }
+
+    impl SelfSigned {
+        fn new(name: &str) -> Self {
+            let generated = rcgen::generate_simple_self_signed(["localhost".to_string()]).unwrap();
+            let dir = std::env::temp_dir();
+            let prefix = format!("milim-tls-{}-{}", std::process::id(), name);
+            let cert = dir.join(format!("{}-cert.pem", prefix));
+            let key = dir.join(format!("{}-key.pem", prefix));
+            std::fs::write(&cert, generated.cert.pem()).unwrap();
+            std::fs::write(&key, generated.signing_key.serialize_pem()).unwrap();
+            let mut roots = RootCertStore::empty();
+            roots.add(generated.cert.der().clone()).unwrap();
+            Self { cert, key, roots }
+        }
+    }
+
+    impl Drop for SelfSigned {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.cert);
+            let _ = std::fs::remove_file(&self.key);
+        }
+    }
+
+    /// Inicia um servidor HTTPS que responde com `is_secure` e `alpn_protocol`
+    async fn start(certs: &SelfSigned) -> SocketAddr {
+        let mut app = server();
+        app.tls(TlsConfig::new(&certs.cert, &certs.key));
+        app.route(Get, "/")
+            .handler(|req: Arc<HttpRequest>, _ctx| async move {
+                let alpn = req.alpn_protocol().unwrap_or_default();
+                format!("{} {}", req.is_secure(), String::from_utf8_lossy(alpn))
+            });
+        let server = app.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+        addr
+    }
+
+    async fn connect(addr: SocketAddr, certs: &SelfSigned, alpn: &[u8]) -> TlsStream<TcpStream> {
+        let mut config = ClientConfig::builder()
+            .with_root_certificates(certs.roots.clone())
+            .with_no_client_auth();
+        config.alpn_protocols = vec![alpn.to_vec()];
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let domain = ServerName::try_from("localhost").unwrap();
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(domain, tcp)
+            .await
+            .unwrap();
+        assert_eq!(stream.get_ref().1.alpn_protocol(), Some(alpn));
+        stream
+    }
+
+    #[tokio::test]
+    async fn reports_secure_connection_over_http1() {
+        let certs = SelfSigned::new("http1");
+        let addr = start(&certs).await;
+
+        let mut stream = connect(addr, &certs, b"http/1.1").await;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        // O servidor pode fechar sem close_notify, o que vira um erro de EOF
+        let _ = stream.read_to_end(&mut out).await;
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"), "{:?}", out);
+        assert!(out.ends_with("\r\n\r\ntrue http/1.1"), "{:?}", out);
+    }
+
+    #[tokio::test]
+    async fn reports_secure_connection_over_h2() {
+        let certs = SelfSigned::new("h2");
+        let addr = start(&certs).await;
+
+        let stream = connect(addr, &certs, b"h2").await;
+        let (mut h2, connection) = h2::client::handshake(stream).await.unwrap();
+        tokio::spawn(connection);
+        let request = http::Request::get("https://localhost/").body(()).unwrap();
+        let (response, _) = h2.send_request(request, true).unwrap();
+        let (parts, mut body) = response.await.unwrap().into_parts();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(parts.status, 200);
+        assert_eq!(Bytes::from(data), "true h2");
+    }
+}
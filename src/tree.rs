@@ -0,0 +1,716 @@
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use regex::Regex;
+
+use crate::{
+    aplication::{percent_decode, percent_encode},
+    error::UrlError,
+    request::Method,
+    router::Router,
+    status::StatusCode,
+};
+
+/// Segmento de um pattern de rota
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    Static(String),
+    /// `:nome` captura um segmento inteiro, `:nome?` tambem aceita a ausencia dele
+    /// e `:nome<restrição>` so aceita valores validos para a restrição
+    Param {
+        name: String,
+        optional: bool,
+        constraint: Option<Constraint>,
+    },
+    /// `*nome` captura um ou mais segmentos finais, `**` aceita nenhum
+    CatchAll {
+        name: String,
+        optional: bool,
+    },
+}
+
+impl Segment {
+    fn is_optional(&self) -> bool {
+        match self {
+            Segment::Static(_) => false,
+            Segment::Param { optional, .. } | Segment::CatchAll { optional, .. } => *optional,
+        }
+    }
+}
+
+/// Pattern de rota ja separado em segmentos, feito uma vez ao registrar a rota
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pattern {
+    pub(crate) segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Faz o parse do pattern, entra em panico se ele for invalido
+    pub(crate) fn parse(raw: &str) -> Self {
+        let segments: Vec<Segment> = split_pattern(raw)
+            .into_iter()
+            .map(|segment| {
+                if segment == "**" {
+                    Segment::CatchAll {
+                        name: "**".to_string(),
+                        optional: true,
+                    }
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::CatchAll {
+                        name: param_name(raw, name).to_string(),
+                        optional: false,
+                    }
+                } else if let Some(spec) = segment.strip_prefix(':') {
+                    // O `?` de opcional fica depois da restrição, `:id<u64>?`
+                    let (spec, optional) = match spec.strip_suffix('?') {
+                        Some(spec) if !spec.contains('<') || spec.ends_with('>') => (spec, true),
+                        _ => (spec, false),
+                    };
+                    let (name, constraint) = match spec.split_once('<') {
+                        Some((name, constraint)) => {
+                            let Some(constraint) = constraint.strip_suffix('>') else {
+                                panic!("Rota invalida {:?}: restrição sem `>`", raw);
+                            };
+                            (name, Some(Constraint::parse(raw, constraint)))
+                        }
+                        None => (spec, None),
+                    };
+                    Segment::Param {
+                        name: param_name(raw, name).to_string(),
+                        optional,
+                        constraint,
+                    }
+                } else {
+                    Segment::Static(segment.to_string())
+                }
+            })
+            .collect();
+
+        let last = segments.len().saturating_sub(1);
+        for (i, segment) in segments.iter().enumerate() {
+            if matches!(segment, Segment::CatchAll { .. }) && i != last {
+                panic!(
+                    "Rota invalida {:?}: o catch-all deve ser o ultimo segmento",
+                    raw
+                );
+            }
+        }
+        if let Some(first) = segments.iter().position(Segment::is_optional)
+            && !segments[first..].iter().all(Segment::is_optional)
+        {
+            panic!(
+                "Rota invalida {:?}: segmentos opcionais devem ser os ultimos",
+                raw
+            );
+        }
+        Self { segments }
+    }
+    /// Adiciona os segmentos de `prefix` no inicio do pattern
+    pub(crate) fn prefix(&mut self, prefix: &str) {
+        let prefix = Pattern::parse(prefix);
+        if prefix
+            .segments
+            .iter()
+            .any(|segment| segment.is_optional() || matches!(segment, Segment::CatchAll { .. }))
+        {
+            panic!("Prefixo invalido: opcionais e catch-all so podem aparecer no fim da rota");
+        }
+        self.segments.splice(0..0, prefix.segments);
+    }
+    /// Monta o caminho com os valores de `params`, percent-encoded
+    pub(crate) fn url(&self, params: &[(&str, &str)]) -> Result<String, UrlError> {
+        if let Some((extra, _)) = params
+            .iter()
+            .find(|(key, _)| !self.param_names().any(|name| name == *key))
+        {
+            return Err(UrlError::ExtraParam(extra.to_string()));
+        }
+        let value_of = |name: &str| params.iter().find(|(key, _)| *key == name).map(|p| p.1);
+        let invalid = |name: &str, value: &str| UrlError::InvalidParam {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+
+        let mut url = String::new();
+        // Um opcional omitido obriga a omitir os seguintes
+        let mut omitted: Option<&str> = None;
+        for segment in self.segments.iter() {
+            let (name, optional) = match segment {
+                Segment::Static(value) => {
+                    url.push('/');
+                    url.push_str(value);
+                    continue;
+                }
+                Segment::Param { name, optional, .. } | Segment::CatchAll { name, optional } => {
+                    (name.as_str(), *optional)
+                }
+            };
+            let Some(value) = value_of(name) else {
+                if !optional {
+                    return Err(UrlError::MissingParam(name.to_string()));
+                }
+                omitted.get_or_insert(name);
+                continue;
+            };
+            if let Some(omitted) = omitted {
+                return Err(UrlError::MissingParam(omitted.to_string()));
+            }
+            match segment {
+                Segment::Param { constraint, .. } => {
+                    if value.is_empty() || constraint.as_ref().is_some_and(|c| !c.matches(value)) {
+                        return Err(invalid(name, value));
+                    }
+                    url.push('/');
+                    url.push_str(&percent_encode(value));
+                }
+                _ => {
+                    // O catch-all mantem as barras entre os segmentos
+                    let value = value.trim_matches('/');
+                    if value.is_empty() && !optional {
+                        return Err(invalid(name, value));
+                    }
+                    for part in value.split('/').filter(|part| !part.is_empty()) {
+                        url.push('/');
+                        url.push_str(&percent_encode(part));
+                    }
+                }
+            }
+        }
+        if url.is_empty() {
+            url.push('/');
+        }
+        Ok(url)
+    }
+    /// Nomes dos parametros na ordem em que aparecem
+    fn param_names(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Param { name, .. } | Segment::CatchAll { name, .. } => Some(name.as_str()),
+            Segment::Static(_) => None,
+        })
+    }
+}
+
+/// Restrição do valor de um parametro.
+///
+/// Tipos como `u64`, `i32`, `f64`, `bool` e `uuid` são reconhecidos pelo nome,
+/// qualquer outro texto e usado como regex que deve casar com o segmento inteiro
+#[derive(Clone)]
+pub(crate) enum Constraint {
+    Type(String, fn(&str) -> bool),
+    Regex(Regex),
+}
+
+impl Constraint {
+    fn parse(raw: &str, source: &str) -> Self {
+        let check: fn(&str) -> bool = match source {
+            "u8" => |v| v.parse::<u8>().is_ok(),
+            "u16" => |v| v.parse::<u16>().is_ok(),
+            "u32" => |v| v.parse::<u32>().is_ok(),
+            "u64" => |v| v.parse::<u64>().is_ok(),
+            "usize" => |v| v.parse::<usize>().is_ok(),
+            "i8" => |v| v.parse::<i8>().is_ok(),
+            "i16" => |v| v.parse::<i16>().is_ok(),
+            "i32" => |v| v.parse::<i32>().is_ok(),
+            "i64" => |v| v.parse::<i64>().is_ok(),
+            "isize" => |v| v.parse::<isize>().is_ok(),
+            "f32" => |v| v.parse::<f32>().is_ok(),
+            "f64" => |v| v.parse::<f64>().is_ok(),
+            "bool" => |v| v.parse::<bool>().is_ok(),
+            "uuid" => is_uuid,
+            _ => {
+                return match Regex::new(&format!("^(?:{})$", source)) {
+                    Ok(regex) => Constraint::Regex(regex),
+                    Err(e) => panic!("Rota invalida {:?}: {}", raw, e),
+                };
+            }
+        };
+        Constraint::Type(source.to_string(), check)
+    }
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Constraint::Type(_, check) => check(value),
+            Constraint::Regex(regex) => regex.is_match(value),
+        }
+    }
+    fn source(&self) -> &str {
+        match self {
+            Constraint::Type(name, _) => name,
+            Constraint::Regex(regex) => regex.as_str(),
+        }
+    }
+}
+
+impl fmt::Debug for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Constraint").field(&self.source()).finish()
+    }
+}
+
+/// Restrições iguais compartilham o mesmo nó da arvore
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        self.source() == other.source()
+    }
+}
+
+/// UUID no formato `8-4-4-4-12` digitos hexadecimais
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn param_name<'a>(raw: &str, name: &'a str) -> &'a str {
+    if name.is_empty() {
+        panic!("Rota invalida {:?}: parametro sem nome", raw);
+    }
+    name
+}
+
+/// Separa o pattern em segmentos como `split_segments`, sem separar nas barras
+/// dentro de uma restrição `<...>`, como em `:path<[^/]+>`
+fn split_pattern(raw: &str) -> Vec<&str> {
+    let pattern = raw.trim_matches('/');
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut in_constraint = false;
+    // Grupos `(?<nome>...)` e classes `[<>]` da regex tambem podem ter `>`
+    let mut groups = 0usize;
+    let mut in_class = false;
+    let mut escaped = false;
+    for (i, c) in pattern.char_indices() {
+        if in_constraint {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '(' if !in_class => groups += 1,
+                ')' if !in_class => groups = groups.saturating_sub(1),
+                '>' if !in_class && groups == 0 => in_constraint = false,
+                _ => {}
+            }
+        } else if c == '<' && pattern[start..].starts_with(':') {
+            in_constraint = true;
+        } else if c == '/' {
+            segments.push(&pattern[start..i]);
+            start = i + 1;
+        }
+    }
+    if !pattern.is_empty() {
+        segments.push(&pattern[start..]);
+    }
+    segments
+}
+
+/// Separa um caminho em segmentos, ignorando as barras do inicio e do fim
+fn split_segments(path: &str) -> impl Iterator<Item = &str> {
+    let path = path.trim_matches('/');
+    path.split('/').filter(move |_| !path.is_empty())
+}
+
+#[derive(Clone, Default)]
+struct Node {
+    statics: HashMap<String, Node>,
+    /// Parametros com restrição vem antes do parametro livre
+    params: Vec<(Option<Constraint>, Node)>,
+    catch_all: Option<Box<Node>>,
+    /// Rotas que terminam neste nó, na ordem de registro
+    routes: Vec<usize>,
+}
+
+impl Node {
+    fn param_child(&mut self, constraint: &Option<Constraint>) -> &mut Node {
+        let position = match self.params.iter().position(|(c, _)| c == constraint) {
+            Some(position) => position,
+            None => {
+                let position = match constraint {
+                    Some(_) => self.params.iter().take_while(|(c, _)| c.is_some()).count(),
+                    None => self.params.len(),
+                };
+                self.params
+                    .insert(position, (constraint.clone(), Node::default()));
+                position
+            }
+        };
+        &mut self.params[position].1
+    }
+}
+
+/// Arvore de rotas indexada por segmentos.
+///
+/// A busca percorre um nó por segmento do caminho, tentando o segmento
+/// estatico, depois os parametros e por ultimo o catch-all, assim `/users/new`
+/// tem prioridade sobre `/users/:id` independente da ordem de registro
+#[derive(Clone, Default)]
+pub(crate) struct RouteTree {
+    root: Node,
+    routes: Vec<Router>,
+}
+
+impl RouteTree {
+    /// Segmentos opcionais fazem a rota terminar em mais de um nó
+    pub(crate) fn insert(&mut self, route: Router) {
+        let index = self.routes.len();
+        let segments = &route.pattern.segments;
+        let required = segments
+            .iter()
+            .position(Segment::is_optional)
+            .unwrap_or(segments.len());
+
+        let mut node = &mut self.root;
+        if required == 0 {
+            node.routes.push(index);
+        }
+        for (depth, segment) in segments.iter().enumerate() {
+            node = match segment {
+                Segment::Static(value) => node.statics.entry(value.clone()).or_default(),
+                Segment::Param { constraint, .. } => node.param_child(constraint),
+                Segment::CatchAll { .. } => node.catch_all.get_or_insert_with(Default::default),
+            };
+            if depth + 1 >= required {
+                node.routes.push(index);
+            }
+        }
+        self.routes.push(route);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Router> {
+        self.routes.iter()
+    }
+    /// Rotas na ordem de registro
+    pub(crate) fn into_routes(self) -> Vec<Router> {
+        self.routes
+    }
+
+    /// Procura a rota para o metodo e caminho, sem a query.
+    ///
+    /// Retorna `MethodNotAllowed` quando o caminho existe apenas para outros metodos
+    /// e `BadRequest` quando um segmento tem um escape invalido
+    pub(crate) fn find(
+        &self,
+        method: &Method,
+        path: &str,
+    ) -> Result<(&Router, HashMap<String, String>), StatusCode> {
+        // Cada segmento e decodificado depois da separação, assim um `%2F`
+        // continua dentro do segmento
+        let decoded = split_segments(path)
+            .map(|segment| percent_decode(segment, false))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.status_code())?;
+        let segments: Vec<&str> = decoded.iter().map(|segment| segment.as_ref()).collect();
+        let mut captures = Vec::new();
+        let mut method_mismatch = false;
+        match self.search(
+            &self.root,
+            &segments,
+            &mut captures,
+            method,
+            &mut method_mismatch,
+        ) {
+            Some(index) => {
+                let route = &self.routes[index];
+                // Parametros opcionais ausentes não entram no mapa
+                let params = route
+                    .pattern
+                    .param_names()
+                    .zip(captures)
+                    .map(|(name, value)| (name.to_string(), value.into_owned()))
+                    .collect();
+                Ok((route, params))
+            }
+            None if method_mismatch => Err(StatusCode::MethodNotAllowed),
+            None => Err(StatusCode::NotFound),
+        }
+    }
+
+    fn search<'p>(
+        &self,
+        node: &Node,
+        segments: &[&'p str],
+        captures: &mut Vec<Cow<'p, str>>,
+        method: &Method,
+        method_mismatch: &mut bool,
+    ) -> Option<usize> {
+        let Some((segment, rest)) = segments.split_first() else {
+            return self.select(node, method, method_mismatch);
+        };
+        if let Some(child) = node.statics.get(*segment)
+            && let Some(found) = self.search(child, rest, captures, method, method_mismatch)
+        {
+            return Some(found);
+        }
+        // Uma restrição que falha segue para o proximo parametro ou catch-all
+        for (constraint, child) in node.params.iter() {
+            if segment.is_empty() || constraint.as_ref().is_some_and(|c| !c.matches(segment)) {
+                continue;
+            }
+            captures.push(Cow::Borrowed(segment));
+            if let Some(found) = self.search(child, rest, captures, method, method_mismatch) {
+                return Some(found);
+            }
+            captures.pop();
+        }
+        if let Some(child) = &node.catch_all
+            && let Some(found) = self.select(child, method, method_mismatch)
+        {
+            captures.push(Cow::Owned(segments.join("/")));
+            return Some(found);
+        }
+        None
+    }
+
+    /// Primeira rota registrada no nó para o metodo
+    fn select(&self, node: &Node, method: &Method, method_mismatch: &mut bool) -> Option<usize> {
+        let found = node
+            .routes
+            .iter()
+            .copied()
+            .find(|index| self.routes[*index].method == *method);
+        *method_mismatch |= found.is_none() && !node.routes.is_empty();
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Pattern, RouteTree};
+    use crate::{
+        context::Context,
+        handler::IntoHandler,
+        request::{HttpRequest, Method, Method::*},
+        router::Router,
+        status::StatusCode,
+    };
+
+    /// Arvore com as rotas na ordem informada, o nome de cada rota e o pattern
+    fn tree(routes: &[(Method, &str)]) -> RouteTree {
+        let mut tree = RouteTree::default();
+        for (method, pattern) in routes {
+            let handler =
+                (|_req: Arc<HttpRequest>, _ctx: Arc<Context>| async move { "" }).into_handler();
+            let mut route = Router::new(pattern, handler, method.clone(), Vec::new());
+            route.name = Some(pattern.to_string());
+            tree.insert(route);
+        }
+        tree
+    }
+
+    /// Pattern da rota encontrada e os parametros em ordem alfabetica
+    fn find(
+        tree: &RouteTree,
+        method: Method,
+        path: &str,
+    ) -> Result<(String, Vec<(String, String)>), StatusCode> {
+        let (route, params) = tree.find(&method, path)?;
+        let mut params: Vec<_> = params.into_iter().collect();
+        params.sort();
+        Ok((route.name.clone().unwrap(), params))
+    }
+
+    fn param(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn static_segment_wins_over_param_in_any_order() {
+        for routes in [
+            [(Get, "/users/:id"), (Get, "/users/new")],
+            [(Get, "/users/new"), (Get, "/users/:id")],
+        ] {
+            let tree = tree(&routes);
+            assert_eq!(
+                find(&tree, Get, "/users/new"),
+                Ok(("/users/new".into(), vec![]))
+            );
+            assert_eq!(
+                find(&tree, Get, "/users/42"),
+                Ok(("/users/:id".into(), vec![param("id", "42")]))
+            );
+        }
+    }
+
+    #[test]
+    fn backtracks_from_static_branch_to_param() {
+        let tree = tree(&[(Get, "/users/new/form"), (Get, "/users/:id/edit")]);
+        assert_eq!(
+            find(&tree, Get, "/users/new/edit"),
+            Ok(("/users/:id/edit".into(), vec![param("id", "new")]))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_param_when_static_only_matches_other_method() {
+        let tree = tree(&[(Get, "/users/new"), (Post, "/users/:id")]);
+        assert_eq!(
+            find(&tree, Post, "/users/new"),
+            Ok(("/users/:id".into(), vec![param("id", "new")]))
+        );
+    }
+
+    #[test]
+    fn answers_405_only_when_path_exists_for_other_method() {
+        let tree = tree(&[(Get, "/users"), (Post, "/users")]);
+        assert_eq!(
+            find(&tree, Delete, "/users"),
+            Err(StatusCode::MethodNotAllowed)
+        );
+        assert_eq!(find(&tree, Get, "/posts"), Err(StatusCode::NotFound));
+    }
+
+    #[test]
+    fn ignores_leading_and_trailing_slashes() {
+        let tree = tree(&[(Get, "/"), (Get, "/a/b/")]);
+        assert_eq!(find(&tree, Get, "/"), Ok(("/".into(), vec![])));
+        assert_eq!(find(&tree, Get, "/a/b"), Ok(("/a/b/".into(), vec![])));
+        assert_eq!(find(&tree, Get, "/a/b/"), Ok(("/a/b/".into(), vec![])));
+    }
+
+    #[test]
+    fn first_registered_route_wins_for_same_pattern() {
+        let tree = tree(&[(Get, "/x/:a"), (Get, "/x/:b")]);
+        assert_eq!(
+            find(&tree, Get, "/x/1"),
+            Ok(("/x/:a".into(), vec![param("a", "1")]))
+        );
+    }
+
+    #[test]
+    fn optional_param_matches_with_and_without_segment() {
+        let tree = tree(&[(Get, "/posts/:id?")]);
+        assert_eq!(
+            find(&tree, Get, "/posts"),
+            Ok(("/posts/:id?".into(), vec![]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/posts/7"),
+            Ok(("/posts/:id?".into(), vec![param("id", "7")]))
+        );
+        assert_eq!(find(&tree, Get, "/posts/7/x"), Err(StatusCode::NotFound));
+    }
+
+    #[test]
+    fn catch_all_captures_remaining_segments() {
+        let tree = tree(&[(Get, "/files/*path"), (Get, "/static/**")]);
+        assert_eq!(
+            find(&tree, Get, "/files/a/b/c.txt"),
+            Ok(("/files/*path".into(), vec![param("path", "a/b/c.txt")]))
+        );
+        assert_eq!(find(&tree, Get, "/files"), Err(StatusCode::NotFound));
+        assert_eq!(
+            find(&tree, Get, "/static"),
+            Ok(("/static/**".into(), vec![]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/static/css/app.css"),
+            Ok(("/static/**".into(), vec![param("**", "css/app.css")]))
+        );
+    }
+
+    #[test]
+    fn catch_all_has_lowest_priority() {
+        let tree = tree(&[
+            (Get, "/files/*path"),
+            (Get, "/files/:name"),
+            (Get, "/files/index"),
+        ]);
+        assert_eq!(
+            find(&tree, Get, "/files/index"),
+            Ok(("/files/index".into(), vec![]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/files/a"),
+            Ok(("/files/:name".into(), vec![param("name", "a")]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/files/a/b"),
+            Ok(("/files/*path".into(), vec![param("path", "a/b")]))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "o catch-all deve ser o ultimo segmento")]
+    fn rejects_catch_all_before_last_segment() {
+        Pattern::parse("/files/*path/edit");
+    }
+
+    #[test]
+    #[should_panic(expected = "segmentos opcionais devem ser os ultimos")]
+    fn rejects_required_segment_after_optional() {
+        Pattern::parse("/posts/:id?/edit");
+    }
+
+    #[test]
+    #[should_panic(expected = "parametro sem nome")]
+    fn rejects_unnamed_param() {
+        Pattern::parse("/posts/:");
+    }
+
+    #[test]
+    fn failed_constraint_falls_through_to_next_route() {
+        let tree = tree(&[(Get, "/items/:slug"), (Get, "/items/:id<u64>")]);
+        assert_eq!(
+            find(&tree, Get, "/items/42"),
+            Ok(("/items/:id<u64>".into(), vec![param("id", "42")]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/items/chair"),
+            Ok(("/items/:slug".into(), vec![param("slug", "chair")]))
+        );
+    }
+
+    #[test]
+    fn failed_constraint_without_other_route_is_not_found() {
+        let tree = tree(&[(Get, "/items/:id<u64>")]);
+        assert_eq!(find(&tree, Get, "/items/-1"), Err(StatusCode::NotFound));
+    }
+
+    #[test]
+    fn checks_uuid_and_regex_constraints() {
+        let tree = tree(&[
+            (Get, "/u/:id<uuid>"),
+            (Get, "/p/:slug<[a-z0-9-]+>"),
+            (Get, "/f/:path<[a-z/]+>/raw"),
+        ]);
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert!(find(&tree, Get, &format!("/u/{}", uuid)).is_ok());
+        assert!(find(&tree, Get, "/u/67e55044-10b1-426f-9247").is_err());
+        assert!(find(&tree, Get, "/p/hello-world").is_ok());
+        // A regex casa com o segmento inteiro
+        assert!(find(&tree, Get, "/p/Hello").is_err());
+        assert_eq!(
+            find(&tree, Get, "/f/a%2Fb/raw"),
+            Ok(("/f/:path<[a-z/]+>/raw".into(), vec![param("path", "a/b")]))
+        );
+        assert!(find(&tree, Get, "/f/a/b/raw").is_err());
+    }
+
+    #[test]
+    fn parses_optional_constraint_and_regex_groups() {
+        let tree = tree(&[(Get, "/g/:x<(?<n>a>)>?")]);
+        assert_eq!(
+            find(&tree, Get, "/g"),
+            Ok(("/g/:x<(?<n>a>)>?".into(), vec![]))
+        );
+        assert_eq!(
+            find(&tree, Get, "/g/a%3E"),
+            Ok(("/g/:x<(?<n>a>)>?".into(), vec![param("x", "a>")]))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "restrição sem `>`")]
+    fn rejects_unclosed_constraint() {
+        Pattern::parse("/items/:id<u64");
+    }
+
+    #[test]
+    #[should_panic(expected = "Rota invalida")]
+    fn rejects_invalid_regex() {
+        Pattern::parse("/items/:id<[a-z>");
+    }
+}
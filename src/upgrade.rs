@@ -0,0 +1,79 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Conexão que pode ser entregue a outro protocolo apos a resposta
+pub trait Io: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T> Io for T where T: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+/// Conexão entregue apos uma resposta `101 Switching Protocols`.
+///
+/// Os bytes que o cliente ja tinha enviado depois da requisição são lidos primeiro
+pub struct Upgraded {
+    io: Box<dyn Io>,
+    prefix: Bytes,
+}
+
+impl Upgraded {
+    pub(crate) fn new<I: Io>(io: I, prefix: Bytes) -> Self {
+        Self {
+            io: Box::new(io),
+            prefix,
+        }
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if !self.prefix.is_empty() {
+            let n = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..n]);
+            self.prefix.advance(n);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// Executado pelo servidor com a conexão depois de enviar a resposta
+pub(crate) struct OnUpgrade(pub(crate) Box<dyn FnOnce(Upgraded) -> BoxFuture<'static, ()> + Send>);
+
+impl fmt::Debug for OnUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnUpgrade")
+    }
+}
+
+/// Callbacks nunca são iguais
+impl PartialEq for OnUpgrade {
+    fn eq(&self, _other: &Self) -> bool {
+        false
+    }
+}
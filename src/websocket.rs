@@ -0,0 +1,530 @@
+/*!
+Suporte a WebSocket (RFC 6455).
+
+A rota faz o handshake quando a requisição tem `Upgrade: websocket`, os guards
+são executados antes do upgrade
+``` rust
+use futures::StreamExt;
+use milim_web::{
+    request::Method::*,
+    server,
+    websocket::{Message, WebSocket},
+};
+
+let mut app = server();
+app.route(Get, "/ws").websocket(|_req, _ctx, ws: WebSocket| async move {
+    let (mut sender, mut receiver) = ws.split();
+    while let Some(Ok(msg)) = receiver.next().await {
+        if let Message::Text(text) = msg {
+            let _ = sender.send(Message::Text(format!("eco: {}", text))).await;
+        }
+    }
+});
+# use tokio::io::{AsyncReadExt, AsyncWriteExt};
+# milim_web::run_app(|| async {
+# let server = app.bind("127.0.0.1:0").await.unwrap();
+# let mut client = tokio::net::TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+# tokio::spawn(server.serve());
+# client.write_all(b"GET /ws HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+#     Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").await.unwrap();
+# // Frame de texto "oi" mascarado com a chave zero
+# client.write_all(&[0x81, 0x82, 0, 0, 0, 0, b'o', b'i']).await.unwrap();
+# let mut received = Vec::new();
+# while !received.ends_with(b"eco: oi") {
+#     let mut buf = [0; 256];
+#     let n = client.read(&mut buf).await.unwrap();
+#     assert!(n > 0);
+#     received.extend_from_slice(&buf[..n]);
+# }
+# assert!(received.starts_with(b"HTTP/1.1 101"));
+# });
+```
+*/
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, future::BoxFuture, stream::BoxStream};
+use sha1::{Digest, Sha1};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
+    sync::Mutex,
+};
+
+use crate::{
+    context::Context,
+    header::HeaderMap,
+    request::{HttpRequest, Method},
+    response::HttpResponse,
+    status::StatusCode,
+    upgrade::{OnUpgrade, Upgraded},
+};
+
+/// GUID concatenado a `Sec-WebSocket-Key` para gerar `Sec-WebSocket-Accept`
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Tamanho maximo de uma mensagem, somando os fragmentos
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Handler de uma rota WebSocket
+pub type WsHandler = Arc<
+    dyn Fn(Arc<HttpRequest>, Arc<Context>, WebSocket) -> BoxFuture<'static, ()>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+pub trait IntoWsHandler {
+    fn into_ws_handler(self) -> WsHandler;
+}
+
+impl<F, Fut> IntoWsHandler for F
+where
+    F: Fn(Arc<HttpRequest>, Arc<Context>, WebSocket) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn into_ws_handler(self) -> WsHandler {
+        Arc::new(move |req, ctx, ws| Box::pin((self)(req, ctx, ws)))
+    }
+}
+
+/// Mensagem recebida ou enviada pelo WebSocket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Bytes),
+    Ping(Bytes),
+    Pong(Bytes),
+    /// Codigo e motivo do fechamento
+    Close(Option<(u16, String)>),
+}
+
+impl Message {
+    fn opcode(&self) -> u8 {
+        match self {
+            Message::Text(_) => 0x1,
+            Message::Binary(_) => 0x2,
+            Message::Close(_) => 0x8,
+            Message::Ping(_) => 0x9,
+            Message::Pong(_) => 0xA,
+        }
+    }
+    fn payload(&self) -> Vec<u8> {
+        match self {
+            Message::Text(text) => text.as_bytes().to_vec(),
+            Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data.to_vec(),
+            Message::Close(None) => Vec::new(),
+            Message::Close(Some((code, reason))) => {
+                let mut payload = code.to_be_bytes().to_vec();
+                payload.extend_from_slice(reason.as_bytes());
+                payload
+            }
+        }
+    }
+}
+
+/// Conexão WebSocket ja estabelecida
+pub struct WebSocket {
+    sender: WsSender,
+    receiver: WsReceiver,
+}
+
+impl WebSocket {
+    pub(crate) fn new(io: Upgraded) -> Self {
+        let (read, write) = tokio::io::split(io);
+        let write = Arc::new(Mutex::new(Writer {
+            io: write,
+            closed: false,
+        }));
+        Self {
+            sender: WsSender {
+                writer: Arc::clone(&write),
+            },
+            receiver: WsReceiver {
+                inner: receive(read, write),
+            },
+        }
+    }
+    pub async fn send(&mut self, msg: Message) -> io::Result<()> {
+        self.sender.send(msg).await
+    }
+    /// Proxima mensagem, `None` depois do fechamento
+    pub async fn recv(&mut self) -> Option<io::Result<Message>> {
+        self.receiver.next().await
+    }
+    /// Separa o envio do recebimento para usar em tasks diferentes
+    pub fn split(self) -> (WsSender, WsReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+struct Writer {
+    io: WriteHalf<Upgraded>,
+    closed: bool,
+}
+
+impl Writer {
+    async fn write_frame(&mut self, msg: &Message) -> io::Result<()> {
+        if self.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "websocket already closed",
+            ));
+        }
+        if matches!(msg, Message::Close(_)) {
+            self.closed = true;
+        }
+        let payload = msg.payload();
+        // Frames do servidor não usam mascara
+        let mut frame = vec![0x80 | msg.opcode()];
+        match payload.len() {
+            len @ 0..=125 => frame.push(len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&payload);
+        self.io.write_all(&frame).await?;
+        self.io.flush().await
+    }
+}
+
+/// Metade de envio do WebSocket
+#[derive(Clone)]
+pub struct WsSender {
+    writer: Arc<Mutex<Writer>>,
+}
+
+impl WsSender {
+    pub async fn send(&mut self, msg: Message) -> io::Result<()> {
+        self.writer.lock().await.write_frame(&msg).await
+    }
+    /// Envia o frame de fechamento com o codigo e o motivo
+    pub async fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        self.send(Message::Close(Some((code, reason.to_string()))))
+            .await
+    }
+}
+
+/// Metade de recebimento do WebSocket, um `Stream` de mensagens.
+///
+/// Pings são respondidos automaticamente e um Close recebido e devolvido ao cliente
+pub struct WsReceiver {
+    inner: BoxStream<'static, io::Result<Message>>,
+}
+
+impl Stream for WsReceiver {
+    type Item = io::Result<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+async fn read_frame(io: &mut ReadHalf<Upgraded>) -> io::Result<Frame> {
+    let mut head = [0u8; 2];
+    io.read_exact(&mut head).await?;
+    if head[0] & 0x70 != 0 {
+        return Err(protocol_error("reserved bits set"));
+    }
+    // Frames do cliente devem usar mascara
+    if head[1] & 0x80 == 0 {
+        return Err(protocol_error("unmasked client frame"));
+    }
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    let len = match head[1] & 0x7F {
+        126 => io.read_u16().await? as u64,
+        127 => io.read_u64().await?,
+        len => len as u64,
+    };
+    // Close, Ping e Pong não podem ser fragmentados e tem no maximo 125 bytes
+    if opcode & 0x8 != 0 && (!fin || len > 125) {
+        return Err(protocol_error("invalid control frame"));
+    }
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(protocol_error("message too large"));
+    }
+    let mut mask = [0u8; 4];
+    io.read_exact(&mut mask).await?;
+    // O buffer cresce com os bytes recebidos, não com o tamanho declarado
+    let mut payload = Vec::new();
+    (&mut *io).take(len).read_to_end(&mut payload).await?;
+    if payload.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// Le os frames, junta fragmentos e responde pings e o fechamento
+fn receive(
+    read: ReadHalf<Upgraded>,
+    writer: Arc<Mutex<Writer>>,
+) -> BoxStream<'static, io::Result<Message>> {
+    // Opcode e dados da mensagem fragmentada em andamento
+    let partial: Option<(u8, Vec<u8>)> = None;
+    let state = Some((read, writer, partial));
+    futures::stream::unfold(state, |state| async move {
+        let (mut read, writer, mut partial) = state?;
+        loop {
+            let frame = match read_frame(&mut read).await {
+                Ok(frame) => frame,
+                Err(e) => return Some((Err(e), None)),
+            };
+            let msg = match frame.opcode {
+                0x0..=0x2 => {
+                    let (opcode, mut data) = match (frame.opcode, partial.take()) {
+                        (0x0, Some(partial)) => partial,
+                        (0x0, None) => {
+                            return Some((Err(protocol_error("unexpected continuation")), None));
+                        }
+                        (_, Some(_)) => {
+                            return Some((Err(protocol_error("expected continuation")), None));
+                        }
+                        (opcode, None) => (opcode, Vec::new()),
+                    };
+                    data.extend_from_slice(&frame.payload);
+                    if data.len() > MAX_MESSAGE_SIZE {
+                        return Some((Err(protocol_error("message too large")), None));
+                    }
+                    if !frame.fin {
+                        partial = Some((opcode, data));
+                        continue;
+                    }
+                    if opcode == 0x1 {
+                        match String::from_utf8(data) {
+                            Ok(text) => Message::Text(text),
+                            Err(_) => {
+                                return Some((Err(protocol_error("invalid utf-8 text")), None));
+                            }
+                        }
+                    } else {
+                        Message::Binary(Bytes::from(data))
+                    }
+                }
+                0x8 => {
+                    let close = match frame.payload.len() {
+                        0 => None,
+                        1 => return Some((Err(protocol_error("invalid close frame")), None)),
+                        _ => Some((
+                            u16::from_be_bytes([frame.payload[0], frame.payload[1]]),
+                            String::from_utf8_lossy(&frame.payload[2..]).to_string(),
+                        )),
+                    };
+                    let mut writer = writer.lock().await;
+                    if !writer.closed {
+                        let code = close.as_ref().map(|(code, _)| (*code, String::new()));
+                        let _ = writer.write_frame(&Message::Close(code)).await;
+                    }
+                    // Depois do Close o stream termina
+                    return Some((Ok(Message::Close(close)), None));
+                }
+                0x9 => {
+                    let payload = Bytes::from(frame.payload);
+                    let _ = writer
+                        .lock()
+                        .await
+                        .write_frame(&Message::Pong(payload.clone()))
+                        .await;
+                    Message::Ping(payload)
+                }
+                0xA => Message::Pong(Bytes::from(frame.payload)),
+                _ => return Some((Err(protocol_error("unknown opcode")), None)),
+            };
+            return Some((Ok(msg), Some((read, writer, partial))));
+        }
+    })
+    .boxed()
+}
+
+/// Valor de `Sec-WebSocket-Accept` para a chave enviada pelo cliente
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Valida o handshake e responde `101 Switching Protocols`, o handler
+/// recebe a conexão depois que a resposta for enviada
+pub(crate) fn upgrade(
+    req: Arc<HttpRequest>,
+    ctx: Arc<Context>,
+    handler: WsHandler,
+) -> HttpResponse {
+    let headers = &req.raw.headers;
+    let has_token = |name: &str, token: &str| {
+        headers
+            .get_all(name)
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case(token))
+    };
+    if req.raw.method != Method::Get
+        || !has_token("Upgrade", "websocket")
+        || !has_token("Connection", "upgrade")
+    {
+        let mut res = HttpResponse::new(StatusCode::UpgradeRequired, None, "");
+        res.headers_mut().insert("Upgrade", "websocket");
+        return res;
+    }
+    if headers.get("Sec-WebSocket-Version") != Some("13") {
+        let mut res = HttpResponse::new(StatusCode::UpgradeRequired, None, "");
+        res.headers_mut().insert("Sec-WebSocket-Version", "13");
+        return res;
+    }
+    let Some(key) = headers.get("Sec-WebSocket-Key") else {
+        return HttpResponse::new(StatusCode::BadRequest, None, "");
+    };
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert("Upgrade", "websocket");
+    response_headers.insert("Connection", "Upgrade");
+    response_headers.insert("Sec-WebSocket-Accept", &accept_key(key));
+    let mut res = HttpResponse::new(StatusCode::SwitchingProtocols, Some(response_headers), "");
+    res.set_upgrade(OnUpgrade(Box::new(move |io| {
+        Box::pin(async move { handler(req, ctx, WebSocket::new(io)).await })
+    })));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use bytes::Bytes;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex};
+
+    use super::{Message, WebSocket, read_frame};
+    use crate::upgrade::Upgraded;
+
+    /// Frame do cliente com a mascara `1 2 3 4`
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![first];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    /// Le um frame dos bytes enviados pelo cliente
+    async fn read(bytes: &[u8]) -> io::Result<super::Frame> {
+        let (mut client, server) = duplex(1024);
+        client.write_all(bytes).await.unwrap();
+        drop(client);
+        let (mut read, _write) = tokio::io::split(Upgraded::new(server, Bytes::new()));
+        read_frame(&mut read).await
+    }
+
+    #[tokio::test]
+    async fn unmasks_client_frames() {
+        let frame = read(&client_frame(0x81, b"hello")).await.unwrap();
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, 0x1);
+        assert_eq!(frame.payload, b"hello");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_control_frames() {
+        // Ping sem FIN e Close com mais de 125 bytes
+        for bytes in [client_frame(0x09, b"p"), client_frame(0x88, &[0; 126])] {
+            let err = read(&bytes).await.err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "invalid control frame");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_unmasked_and_oversized_frames() {
+        let err = read(&[0x81, 0x02, b'h', b'i']).await.err().unwrap();
+        assert_eq!(err.to_string(), "unmasked client frame");
+
+        let mut huge = vec![0x82, 0x80 | 127];
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+        let err = read(&huge).await.err().unwrap();
+        assert_eq!(err.to_string(), "message too large");
+    }
+
+    #[tokio::test]
+    async fn reports_eof_before_declared_payload() {
+        let mut frame = vec![0x82, 0x80 | 127];
+        frame.extend_from_slice(&(1024u64 * 1024).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0]);
+        frame.extend_from_slice(b"abc");
+        let err = read(&frame).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Le o proximo frame enviado pelo servidor, sem mascara
+    async fn server_frame(client: &mut DuplexStream) -> (u8, Vec<u8>) {
+        let mut head = [0u8; 2];
+        client.read_exact(&mut head).await.unwrap();
+        let mut payload = vec![0u8; (head[1] & 0x7F) as usize];
+        client.read_exact(&mut payload).await.unwrap();
+        (head[0], payload)
+    }
+
+    #[tokio::test]
+    async fn joins_fragments_and_answers_ping_and_close() {
+        let (mut client, server) = duplex(4096);
+        let mut ws = WebSocket::new(Upgraded::new(server, Bytes::new()));
+
+        client.write_all(&client_frame(0x01, b"hel")).await.unwrap();
+        client.write_all(&client_frame(0x89, b"p")).await.unwrap();
+        client.write_all(&client_frame(0x80, b"lo")).await.unwrap();
+        client
+            .write_all(&client_frame(0x88, &[0x03, 0xE8]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ws.recv().await.unwrap().unwrap(),
+            Message::Ping(Bytes::from("p"))
+        );
+        assert_eq!(
+            ws.recv().await.unwrap().unwrap(),
+            Message::Text("hello".to_string())
+        );
+        assert_eq!(
+            ws.recv().await.unwrap().unwrap(),
+            Message::Close(Some((1000, String::new())))
+        );
+        assert!(ws.recv().await.is_none());
+
+        assert_eq!(server_frame(&mut client).await, (0x8A, b"p".to_vec()));
+        assert_eq!(server_frame(&mut client).await, (0x88, vec![0x03, 0xE8]));
+    }
+}